@@ -1,6 +1,6 @@
 use std::fmt::{Debug, Formatter, Error};
 
-use super::Tree;
+use super::{Tree, NodeRef};
 use super::iter::Edge;
 
 impl<T: Debug> Tree<T> {
@@ -81,3 +81,52 @@ impl<T: Debug> Debug for Tree<T> {
         }
     }
 }
+
+impl<'a, T: 'a + Debug> NodeRef<'a, T> {
+    /// Returns a value whose `Debug` implementation renders this node's subtree over multiple
+    /// indented lines, formatting values with `{:#?}`.
+    pub fn debug_pretty(&self) -> impl Debug + 'a {
+        DebugPretty(*self)
+    }
+}
+
+struct DebugPretty<'a, T: 'a>(NodeRef<'a, T>);
+
+impl<'a, T: 'a + Debug> Debug for DebugPretty<'a, T> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let root = self.0;
+        let mut i = String::new();
+
+        for edge in root.traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    if node != root {
+                        writeln!(f)?;
+                    }
+                    if node.has_children() {
+                        write!(f, "{}{:#?} => {{", i, node.value())?;
+                        i.push_str("    ");
+                    } else if node != root {
+                        write!(f, "{}{:#?},", i, node.value())?;
+                    } else {
+                        write!(f, "{}{:#?}", i, node.value())?;
+                    }
+                },
+
+                Edge::Close(node) if node.has_children() => {
+                    let len = i.len() - 4;
+                    i.truncate(len);
+                    if node != root {
+                        write!(f, "\n{}}},", i)?;
+                    } else {
+                        write!(f, "\n{}}}", i)?;
+                    }
+                },
+
+                _ => { },
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::hash::{Hash, Hasher};
+
+use super::{Tree, NodeRef};
+use super::iter::Edge;
+
+impl<T: Hash> Tree<T> {
+    /// Returns a 256-bit digest of the tree's structure and values, including orphans.
+    ///
+    /// Unlike `DefaultHasher`, the hash function is fixed, so the digest of a tree is the same
+    /// across runs and builds on the same platform, provided the values' `Hash` implementations
+    /// are. The digest is not cryptographic.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Fingerprinter::new();
+        for root in self.nodes().filter(|node| node.parent().is_none()) {
+            hasher.write_u8(2);
+            hash_subtree(root, &mut hasher);
+        }
+
+        let mut digest = [0; 32];
+        for (chunk, lane) in digest.chunks_mut(8).zip(hasher.digest().iter()) {
+            chunk.copy_from_slice(&lane.to_le_bytes());
+        }
+        digest
+    }
+}
+
+fn hash_subtree<T: Hash>(root: NodeRef<T>, hasher: &mut Fingerprinter) {
+    for edge in root.traverse() {
+        match edge {
+            Edge::Open(node) => {
+                hasher.write_u8(0);
+                node.value().hash(hasher);
+            },
+            Edge::Close(_) => hasher.write_u8(1),
+        }
+    }
+}
+
+// Four independently seeded FNV-1a lanes, each finished with the SplitMix64 mixer.
+struct Fingerprinter {
+    lanes: [u64; 4],
+    len: u64,
+}
+
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+const SEEDS: [u64; 4] = [
+    0xcbf2_9ce4_8422_2325,
+    0x9e37_79b9_7f4a_7c15,
+    0xbf58_476d_1ce4_e5b9,
+    0x94d0_49bb_1331_11eb,
+];
+
+impl Fingerprinter {
+    fn new() -> Self {
+        Fingerprinter { lanes: SEEDS, len: 0 }
+    }
+
+    fn digest(&self) -> [u64; 4] {
+        let mut digest = [0; 4];
+        for (i, lane) in self.lanes.iter().enumerate() {
+            let mut z = lane ^ self.len.rotate_left(16 * i as u32);
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            digest[i] = z ^ (z >> 31);
+        }
+        digest
+    }
+}
+
+impl Hasher for Fingerprinter {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            for (i, lane) in self.lanes.iter_mut().enumerate() {
+                *lane = (*lane ^ u64::from(byte)).wrapping_mul(FNV_PRIME).rotate_left(i as u32 * 7);
+            }
+        }
+        self.len = self.len.wrapping_add(bytes.len() as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.digest()[0]
+    }
+}
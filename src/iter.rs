@@ -16,7 +16,7 @@ impl<'a, T: 'a> Iterator for Values<'a, T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<&'a T> {
-        self.inner.next().map(|n| &n.value)
+        self.inner.by_ref().filter_map(|n| n.value.as_ref()).next()
     }
 }
 
@@ -35,7 +35,7 @@ impl<'a, T: 'a> Iterator for ValuesMut<'a, T> {
     type Item = &'a mut T;
 
     fn next(&mut self) -> Option<&'a mut T> {
-        self.inner.next().map(|n| &mut n.value)
+        self.inner.by_ref().filter_map(|n| n.value.as_mut()).next()
     }
 }
 
@@ -54,7 +54,7 @@ impl<T> Iterator for IntoValues<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<T> {
-        self.inner.next().map(|n| n.value)
+        self.inner.by_ref().filter_map(|n| n.value).next()
     }
 }
 
@@ -74,7 +74,7 @@ impl<'a, T: 'a> Iterator for Nodes<'a, T> {
     type Item = NodeRef<'a, T>;
 
     fn next(&mut self) -> Option<NodeRef<'a, T>> {
-        self.inner.next().map(|(index, node)| {
+        self.inner.by_ref().find(|&(_, node)| !node.is_removed()).map(|(index, node)| {
             NodeRef {
                 tree: self.tree,
                 node: node,
@@ -92,6 +92,20 @@ impl<'a, T: 'a + fmt::Debug> fmt::Debug for Nodes<'a, T> {
     }
 }
 
+/// Iterator over all node IDs.
+#[derive(Debug)]
+pub struct NodeIds<'a, T: 'a> {
+    inner: Nodes<'a, T>,
+}
+
+impl<'a, T: 'a> Iterator for NodeIds<'a, T> {
+    type Item = NodeId<T>;
+
+    fn next(&mut self) -> Option<NodeId<T>> {
+        self.inner.next().map(|node| node.id())
+    }
+}
+
 impl<T> Tree<T> {
     /// Returns an iterator over node values in creation order.
     pub fn values(&self) -> Values<T> {
@@ -108,13 +122,27 @@ impl<T> Tree<T> {
         IntoValues { inner: self.vec.into_iter() }
     }
 
-    /// Returns an iterator over all nodes, including orphans, in creation order.
+    /// Returns an iterator over all nodes, including orphans but not removed nodes, in creation
+    /// order.
     pub fn nodes(&self) -> Nodes<T> {
         Nodes {
             tree: self,
             inner: self.vec.iter().enumerate(),
         }
     }
+
+    /// Returns an iterator over the IDs of all nodes, including orphans but not removed nodes, in
+    /// creation order.
+    pub fn node_ids(&self) -> NodeIds<'_, T> {
+        NodeIds { inner: self.nodes() }
+    }
+
+    /// Returns an iterator over `(parent, child)` ID pairs of the nodes reachable from the root.
+    ///
+    /// Pairs are ordered by the position of the child in pre-order.
+    pub fn edges(&self) -> Edges<'_, T> {
+        Edges { traverse: self.root().traverse() }
+    }
 }
 
 macro_rules! axis_iterators {
@@ -290,6 +318,305 @@ impl<'a, T: 'a> PartialEq for Traverse<'a, T> {
     }
 }
 
+/// Iterator over node ancestors up to, but not including, a boundary node.
+#[derive(Debug)]
+pub struct AncestorsUntil<'a, T: 'a> {
+    node: Option<NodeRef<'a, T>>,
+    stop: NodeId<T>,
+}
+
+impl<'a, T: 'a> Iterator for AncestorsUntil<'a, T> {
+    type Item = NodeRef<'a, T>;
+
+    fn next(&mut self) -> Option<NodeRef<'a, T>> {
+        let node = self.node.take()?;
+        if node.id() == self.stop {
+            return None;
+        }
+        self.node = node.parent();
+        Some(node)
+    }
+}
+
+impl<'a, T: 'a> Copy for AncestorsUntil<'a, T> { }
+impl<'a, T: 'a> Clone for AncestorsUntil<'a, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+/// Iterator over pairs of adjacent node children.
+#[derive(Debug)]
+pub struct ChildWindows<'a, T: 'a> {
+    node: Option<NodeRef<'a, T>>,
+}
+
+impl<'a, T: 'a> Iterator for ChildWindows<'a, T> {
+    type Item = (NodeRef<'a, T>, NodeRef<'a, T>);
+
+    fn next(&mut self) -> Option<(NodeRef<'a, T>, NodeRef<'a, T>)> {
+        let left = self.node?;
+        let right = left.next_sibling();
+        self.node = right;
+        right.map(|right| (left, right))
+    }
+}
+
+impl<'a, T: 'a> Copy for ChildWindows<'a, T> { }
+impl<'a, T: 'a> Clone for ChildWindows<'a, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+/// Iterator over node descendants, in pre-order.
+#[derive(Debug)]
+pub struct Descendants<'a, T: 'a> {
+    front: Option<NodeRef<'a, T>>,
+    back: Option<NodeRef<'a, T>>,
+}
+
+impl<'a, T: 'a> Iterator for Descendants<'a, T> {
+    type Item = NodeRef<'a, T>;
+
+    fn next(&mut self) -> Option<NodeRef<'a, T>> {
+        if self.front == self.back {
+            let node = self.front.take();
+            self.back = None;
+            node
+        } else {
+            let node = self.front.take();
+            self.front = node.as_ref().and_then(NodeRef::next_pre_order);
+            node
+        }
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for Descendants<'a, T> {
+    fn next_back(&mut self) -> Option<NodeRef<'a, T>> {
+        if self.back == self.front {
+            let node = self.back.take();
+            self.front = None;
+            node
+        } else {
+            let node = self.back.take();
+            self.back = node.as_ref().and_then(NodeRef::prev_pre_order);
+            node
+        }
+    }
+}
+
+impl<'a, T: 'a> Copy for Descendants<'a, T> { }
+impl<'a, T: 'a> Clone for Descendants<'a, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<'a, T: 'a> Eq for Descendants<'a, T> { }
+impl<'a, T: 'a> PartialEq for Descendants<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.front == other.front && self.back == other.back
+    }
+}
+
+impl<'a, T: 'a> Descendants<'a, T> {
+    /// Groups the remaining descendants into runs of consecutive nodes at the same depth.
+    pub fn chunk_by_depth(self) -> ChunkByDepth<'a, T> {
+        ChunkByDepth { descendants: self, pending: None }
+    }
+
+    /// Pairs each remaining descendant with the change in depth from the node yielded before it.
+    ///
+    /// The change is positive when descending and negative when returning up one or more levels.
+    /// The first node's change is measured from its parent, so it is always 1.
+    pub fn depth_transitions(self) -> DepthTransitions<'a, T> {
+        DepthTransitions { descendants: self, prev: None }
+    }
+}
+
+// Returns the change in depth from a node to a node following it in pre-order.
+fn depth_change<'a, T: 'a>(prev: NodeRef<'a, T>, node: NodeRef<'a, T>) -> i32 {
+    if node.parent() == Some(prev) {
+        return 1;
+    }
+    // The node's parent is an ancestor of the previous node.
+    let mut ancestor = prev;
+    let mut change = 0;
+    while ancestor.parent() != node.parent() {
+        ancestor = ancestor.parent().unwrap();
+        change -= 1;
+    }
+    change
+}
+
+/// Iterator over runs of consecutive pre-order descendants at the same depth.
+#[derive(Debug)]
+pub struct ChunkByDepth<'a, T: 'a> {
+    descendants: Descendants<'a, T>,
+    pending: Option<NodeRef<'a, T>>,
+}
+
+impl<'a, T: 'a> Iterator for ChunkByDepth<'a, T> {
+    type Item = Vec<NodeRef<'a, T>>;
+
+    fn next(&mut self) -> Option<Vec<NodeRef<'a, T>>> {
+        let first = match self.pending.take() {
+            Some(node) => node,
+            None => self.descendants.next()?,
+        };
+
+        let mut chunk = vec![first];
+        for node in &mut self.descendants {
+            if depth_change(*chunk.last().unwrap(), node) != 0 {
+                self.pending = Some(node);
+                break;
+            }
+            chunk.push(node);
+        }
+        Some(chunk)
+    }
+}
+
+impl<'a, T: 'a> Copy for ChunkByDepth<'a, T> { }
+impl<'a, T: 'a> Clone for ChunkByDepth<'a, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+/// Iterator over pre-order descendants paired with their changes in depth.
+#[derive(Debug)]
+pub struct DepthTransitions<'a, T: 'a> {
+    descendants: Descendants<'a, T>,
+    prev: Option<NodeRef<'a, T>>,
+}
+
+impl<'a, T: 'a> Iterator for DepthTransitions<'a, T> {
+    type Item = (NodeRef<'a, T>, i32);
+
+    fn next(&mut self) -> Option<(NodeRef<'a, T>, i32)> {
+        let node = self.descendants.next()?;
+        let change = self.prev.map_or(1, |prev| depth_change(prev, node));
+        self.prev = Some(node);
+        Some((node, change))
+    }
+}
+
+impl<'a, T: 'a> Copy for DepthTransitions<'a, T> { }
+impl<'a, T: 'a> Clone for DepthTransitions<'a, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+/// Iterator over node descendants within a maximum depth, in pre-order.
+#[derive(Debug)]
+pub struct DescendantsWithin<'a, T: 'a> {
+    stack: Vec<(NodeRef<'a, T>, usize)>,
+    max_depth: usize,
+}
+
+impl<'a, T: 'a> Iterator for DescendantsWithin<'a, T> {
+    type Item = NodeRef<'a, T>;
+
+    fn next(&mut self) -> Option<NodeRef<'a, T>> {
+        let (node, depth) = self.stack.pop()?;
+        if depth < self.max_depth {
+            self.stack.extend(node.children().rev().map(|child| (child, depth + 1)));
+        }
+        Some(node)
+    }
+}
+
+impl<'a, T: 'a> Clone for DescendantsWithin<'a, T> {
+    fn clone(&self) -> Self {
+        DescendantsWithin { stack: self.stack.clone(), max_depth: self.max_depth }
+    }
+}
+
+/// Iterator over node descendant IDs, in breadth-first order.
+#[derive(Debug)]
+pub struct DescendantIdsBfs<'a, T: 'a> {
+    queue: collections::VecDeque<NodeRef<'a, T>>,
+}
+
+impl<'a, T: 'a> Iterator for DescendantIdsBfs<'a, T> {
+    type Item = NodeId<T>;
+
+    fn next(&mut self) -> Option<NodeId<T>> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children());
+        Some(node.id())
+    }
+}
+
+impl<'a, T: 'a> Clone for DescendantIdsBfs<'a, T> {
+    fn clone(&self) -> Self {
+        DescendantIdsBfs { queue: self.queue.clone() }
+    }
+}
+
+/// Iterator over node descendants with their paths of child positions, in pre-order.
+#[derive(Debug)]
+pub struct DescendantsWithPaths<'a, T: 'a> {
+    traverse: Traverse<'a, T>,
+    path: Vec<usize>,
+    next_position: usize,
+}
+
+impl<'a, T: 'a> Iterator for DescendantsWithPaths<'a, T> {
+    type Item = (Vec<usize>, NodeRef<'a, T>);
+
+    fn next(&mut self) -> Option<(Vec<usize>, NodeRef<'a, T>)> {
+        while let Some(edge) = self.traverse.next() {
+            match edge {
+                Edge::Open(node) if node == self.traverse.root => { },
+                Edge::Open(node) => {
+                    let position = match node.prev_sibling() {
+                        Some(_) => self.next_position,
+                        None => 0,
+                    };
+                    self.path.push(position);
+                    return Some((self.path.clone(), node));
+                },
+                Edge::Close(_) => {
+                    if let Some(position) = self.path.pop() {
+                        self.next_position = position + 1;
+                    }
+                },
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T: 'a> Clone for DescendantsWithPaths<'a, T> {
+    fn clone(&self) -> Self {
+        DescendantsWithPaths {
+            traverse: self.traverse,
+            path: self.path.clone(),
+            next_position: self.next_position,
+        }
+    }
+}
+
+/// Iterator over parent-child ID pairs.
+#[derive(Debug)]
+pub struct Edges<'a, T: 'a> {
+    traverse: Traverse<'a, T>,
+}
+
+impl<'a, T: 'a> Iterator for Edges<'a, T> {
+    type Item = (NodeId<T>, NodeId<T>);
+
+    fn next(&mut self) -> Option<(NodeId<T>, NodeId<T>)> {
+        for edge in &mut self.traverse {
+            if let Edge::Open(node) = edge {
+                if let Some(parent) = node.parent() {
+                    return Some((parent.id(), node.id()));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T: 'a> Copy for Edges<'a, T> { }
+impl<'a, T: 'a> Clone for Edges<'a, T> {
+    fn clone(&self) -> Self { *self }
+}
+
 /// Fitler iterator over child elements.
 /// If child nodes is processed then parent nodes will be ignored.
 #[derive(Debug,Clone)]
@@ -332,6 +659,18 @@ impl<'a, T: 'a> NodeRef<'a, T> {
         Ancestors { node: self.parent() }
     }
 
+    /// Returns an iterator over this node's ancestors up to, but not including, the node `stop`.
+    ///
+    /// If `stop` is not an ancestor of this node, all of its ancestors are yielded.
+    pub fn ancestors_until(&self, stop: NodeId<T>) -> AncestorsUntil<'a, T> {
+        AncestorsUntil { node: self.parent(), stop }
+    }
+
+    /// Returns an iterator over this node and its ancestors, starting with this node.
+    pub fn ancestors_inclusive(&self) -> Ancestors<'a, T> {
+        Ancestors { node: Some(*self) }
+    }
+
     /// Returns an iterator over this node's previous siblings.
     pub fn prev_siblings(&self) -> PrevSiblings<'a, T> {
         PrevSiblings { node: self.prev_sibling() }
@@ -350,6 +689,11 @@ impl<'a, T: 'a> NodeRef<'a, T> {
         }
     }
 
+    /// Returns an iterator over each pair of adjacent children of this node, in order.
+    pub fn child_windows(&self) -> ChildWindows<'a, T> {
+        ChildWindows { node: self.first_child() }
+    }
+
     /// Returns an iterator over this node's first children.
     pub fn first_children(&self) -> FirstChildren<'a, T> {
         FirstChildren { node: self.first_child() }
@@ -360,6 +704,39 @@ impl<'a, T: 'a> NodeRef<'a, T> {
         LastChildren { node: self.last_child() }
     }
 
+    /// Returns an iterator over this node's descendants, not including itself, in pre-order.
+    pub fn descendants(&self) -> Descendants<'a, T> {
+        Descendants {
+            front: self.first_child(),
+            back: self.last_children().last(),
+        }
+    }
+
+    /// Returns an iterator over this node's descendants, not including itself, in pre-order, each
+    /// with its path of child positions from this node.
+    pub fn descendants_with_paths(&self) -> DescendantsWithPaths<'a, T> {
+        DescendantsWithPaths { traverse: self.traverse(), path: Vec::new(), next_position: 0 }
+    }
+
+    /// Returns an iterator over this node's descendants at most `max_relative_depth` levels below
+    /// it, in pre-order.
+    ///
+    /// A depth of 1 yields only this node's children. Deeper subtrees are not walked.
+    pub fn descendants_within(&self, max_relative_depth: usize) -> DescendantsWithin<'a, T> {
+        let stack = if max_relative_depth == 0 {
+            Vec::new()
+        } else {
+            self.children().rev().map(|child| (child, 1)).collect()
+        };
+        DescendantsWithin { stack, max_depth: max_relative_depth }
+    }
+
+    /// Returns an iterator over the IDs of this node's descendants, not including itself, level
+    /// by level.
+    pub fn descendant_ids_bfs(&self) -> DescendantIdsBfs<'a, T> {
+        DescendantIdsBfs { queue: self.children().collect() }
+    }
+
     /// Returns an iterator which traverses the subtree starting at this node.
     pub fn traverse(&self) -> Traverse<'a, T> {
         Traverse {
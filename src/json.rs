@@ -0,0 +1,27 @@
+use serde_json::{Map, Value};
+
+use super::Tree;
+use super::iter::Edge;
+
+impl<T> Tree<T> {
+    /// Converts the tree to a JSON value, converting each node's value with `value_fn`.
+    ///
+    /// Each node becomes an object with a `"value"` member and a `"children"` array. Orphans are
+    /// not included. Requires the `json` feature.
+    pub fn to_json<F>(&self, value_fn: F) -> Value where F: Fn(&T) -> Value {
+        let mut stack: Vec<Vec<Value>> = vec![Vec::new()];
+        for edge in self.root().traverse() {
+            match edge {
+                Edge::Open(_) => stack.push(Vec::new()),
+                Edge::Close(node) => {
+                    let children = stack.pop().unwrap();
+                    let mut object = Map::new();
+                    let _ = object.insert(String::from("value"), value_fn(node.value()));
+                    let _ = object.insert(String::from("children"), Value::Array(children));
+                    stack.last_mut().unwrap().push(Value::Object(object));
+                },
+            }
+        }
+        stack.pop().unwrap().pop().unwrap()
+    }
+}
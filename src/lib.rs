@@ -9,13 +9,20 @@
 //!
 //! - Nodes have zero or more ordered children.
 //! - Nodes have at most one parent; orphan nodes are valid.
-//! - Individual nodes are not dropped until the tree is dropped.
+//! - Nodes are never moved in memory by ordinary operations. Removing a node drops its value
+//!   and leaves its slot vacant; slots are never reused, so a removed node's ID is never given to
+//!   another node.
 //! - A node's parent, next sibling, previous sibling, first child and last child can be accessed
 //!   in constant time.
-//! - Node IDs act as weak references, i.e. they are not tied to the lifetime of the tree.
+//! - Node IDs act as weak references, i.e. they are not tied to the lifetime of the tree. An ID
+//!   of a removed node is detected when used: `Tree::get` panics and `Tree::try_validate` returns
+//!   `IdError::Removed`.
+//! - Methods that rebuild the storage, such as `Tree::canonicalize` and
+//!   `Tree::rebuild_in_preorder`, drop vacant slots and invalidate all existing IDs.
 //!
-//! All methods in this crate execute in constant time, and all iterators execute to completion in
-//! linear time.
+//! Accessors and single-node edits execute in constant time, and all iterators execute to
+//! completion in linear time. Methods which inspect or rearrange whole subtrees or trees document
+//! their cost where it is not evident.
 //!
 //! # Examples
 //!
@@ -59,19 +66,34 @@
 // Clippy.
 #![allow(unknown_lints)]
 
+#[cfg(feature = "json")]
+extern crate serde_json;
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::iter::Sum;
 use std::marker::PhantomData;
+use std::mem;
 use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
 
+use iter::Edge;
+
 /// A Vec-backed tree.
 ///
 /// Nodes are allocated in a `Vec` which is only ever pushed to. `NodeId` is an opaque index into
-/// the `Vec`.
+/// the `Vec`. A removed node leaves a vacant slot behind, holding no value, which is skipped by
+/// iteration and rejected by ID validation. `len` counts only occupied slots.
 ///
 /// Each `Tree` has a unique ID which is also given to each `NodeId` it creates. This is used to
 /// bounds check a `NodeId`.
 pub struct Tree<T> {
     id: usize,
     vec: Vec<Node<T>>,
+    removed: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -80,13 +102,13 @@ struct Node<T> {
     prev_sibling: Option<usize>,
     next_sibling: Option<usize>,
     children: Option<(usize, usize)>,
-    value: T,
+    value: Option<T>,
 }
 
 /// A node ID.
 ///
 /// `NodeId` acts as a weak reference which is not tied to the lifetime of the `Tree` that created
-/// it.
+/// it. It remains valid until its node is removed, after which using it with the tree panics.
 ///
 /// With the original `Tree`, a `NodeId` can be used to obtain a `NodeRef` or `NodeMut`.
 ///
@@ -131,11 +153,220 @@ pub struct NodeMut<'a, T: 'a> {
     index: usize,
 }
 
+/// A visitor of nodes, used by `NodeRef::walk`.
+///
+/// Both methods do nothing by default.
+pub trait Visitor<T> {
+    /// Called when a node is entered, before its children.
+    fn enter(&mut self, _node: NodeRef<T>) { }
+
+    /// Called when a node is left, after its children.
+    fn leave(&mut self, _node: NodeRef<T>) { }
+}
+
+/// A visitor of nodes with mutable access to their values, used by `Tree::walk_mut`.
+///
+/// Each method receives the node's ID, its depth from the root and its value. Both methods do
+/// nothing by default.
+pub trait VisitorMut<T> {
+    /// Called when a node is entered, before its children.
+    fn enter(&mut self, _id: NodeId<T>, _depth: usize, _value: &mut T) { }
+
+    /// Called when a node is left, after its children.
+    fn leave(&mut self, _id: NodeId<T>, _depth: usize, _value: &mut T) { }
+}
+
+/// A problem encountered by `Tree::build_lenient`.
+///
+/// Rows are identified by their position in the input. A row with a warning is left in the tree
+/// as an orphan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildWarning {
+    /// The row's parent index does not refer to a row.
+    MissingParent {
+        /// Position of the row.
+        row: usize,
+        /// Parent index given for the row.
+        parent: usize,
+    },
+
+    /// Linking the row to its parent would create a cycle.
+    Cycle {
+        /// Position of the row.
+        row: usize,
+        /// Parent index given for the row.
+        parent: usize,
+    },
+}
+
+/// An error encountered by `Tree::from_sexpr` or `Tree::from_indented`.
+///
+/// Offsets are in bytes from the start of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The input, or a group, ended where a value was expected.
+    ExpectedValue {
+        /// Offset at which a value was expected.
+        offset: usize,
+    },
+
+    /// A group contains no values.
+    EmptyParens {
+        /// Offset of the opening parenthesis.
+        offset: usize,
+    },
+
+    /// A group is never closed.
+    UnclosedParen {
+        /// Offset of the opening parenthesis.
+        offset: usize,
+    },
+
+    /// A closing parenthesis has no matching opening parenthesis.
+    UnopenedParen {
+        /// Offset of the closing parenthesis.
+        offset: usize,
+    },
+
+    /// A quoted value is never closed.
+    UnclosedQuote {
+        /// Offset of the opening quote.
+        offset: usize,
+    },
+
+    /// Input continues after the root value or group.
+    TrailingInput {
+        /// Offset of the first trailing token.
+        offset: usize,
+    },
+
+    /// A line's indentation is not a whole number of indent units.
+    BadIndent {
+        /// Offset of the start of the line.
+        offset: usize,
+    },
+
+    /// A line is indented more than one level deeper than the line before it.
+    IndentJump {
+        /// Offset of the start of the line.
+        offset: usize,
+    },
+
+    /// A line is indented with a tab; only spaces count as indentation.
+    TabIndent {
+        /// Offset of the tab.
+        offset: usize,
+    },
+}
+
+/// An error returned by `Tree::try_validate` for an ID that does not refer to a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdError {
+    /// The ID was created by a different tree.
+    WrongTree,
+    /// The ID's index is beyond the nodes of the tree.
+    OutOfRange,
+    /// The ID refers to a node which has been removed.
+    Removed,
+}
+
+/// An inconsistency in the links between nodes, found by `Tree::sanity_check` or
+/// `Tree::from_parts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inconsistency {
+    /// Index in the tree's storage of the node at fault.
+    pub node: usize,
+    /// What is wrong with the node.
+    pub kind: InconsistencyKind,
+}
+
+/// The kind of an `Inconsistency`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InconsistencyKind {
+    /// There are no nodes at all; the node at fault is 0.
+    NoRoot,
+    /// The root has been removed.
+    RootRemoved,
+    /// The root has a parent.
+    RootHasParent,
+    /// A link refers to an index beyond the nodes.
+    LinkOutOfRange,
+    /// A removed node still has links.
+    RemovedWithLinks,
+    /// The node's children include a removed node.
+    RemovedChild,
+    /// The node is among another node's children, but does not have it as its parent.
+    WrongParent,
+    /// The node's previous sibling does not match its position among its parent's children.
+    WrongPrevSibling,
+    /// The sibling links among the node's children form a cycle.
+    SiblingCycle,
+    /// The node's last child is not where its children end.
+    WrongLastChild,
+    /// The node has a parent, but is not among its parent's children.
+    MissingFromParent,
+    /// The node has no parent, but has a previous or next sibling.
+    ParentlessWithSiblings,
+    /// The node is its own ancestor.
+    ParentCycle,
+}
+
+/// The links of a node, as exposed by `Tree::into_parts` and `Tree::from_parts`.
+///
+/// Each link is an index into the tree's storage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Links {
+    /// The node's parent.
+    pub parent: Option<usize>,
+    /// The node's previous sibling.
+    pub prev_sibling: Option<usize>,
+    /// The node's next sibling.
+    pub next_sibling: Option<usize>,
+    /// The node's first and last children.
+    pub children: Option<(usize, usize)>,
+}
+
+/// A subtree built apart from any tree, to be attached to one with `Tree::attach_subtree`.
+///
+/// Nodes are identified by their position in the order they were added; the root is `0`.
+#[derive(Debug, Clone)]
+pub struct Subtree<T> {
+    nodes: Vec<Removed<T>>,
+}
+
+/// The first structural difference between two trees, as found by `Tree::shape_mismatch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    /// The child positions leading from the root to the differing node.
+    pub path: Vec<usize>,
+    /// The number of children of the node in the tree `shape_mismatch` was called on.
+    pub expected_children: usize,
+    /// The number of children of the node in the other tree.
+    pub actual_children: usize,
+}
+
+/// A position relative to a parent's children, used by `NodeMut::reattach_to`.
+#[derive(Debug)]
+pub enum Position<T> {
+    /// Before all other children.
+    First,
+    /// After all other children.
+    Last,
+    /// Immediately before the specified child.
+    Before(NodeId<T>),
+    /// Immediately after the specified child.
+    After(NodeId<T>),
+}
+
 // Implementations.
 mod node_id;
 mod node_ref;
 mod node_mut;
 mod debug;
+mod text;
+mod fingerprint;
+#[cfg(feature = "json")]
+mod json;
 
 pub mod iter;
 
@@ -143,6 +374,13 @@ pub mod iter;
 static TREE_ID_SEQ: AtomicUsize = ATOMIC_USIZE_INIT;
 fn tree_id_seq_next() -> usize { TREE_ID_SEQ.fetch_add(1, Ordering::Relaxed) }
 
+// A node removed from a tree, with the position of its parent among the nodes removed with it.
+#[derive(Debug, Clone)]
+struct Removed<T> {
+    parent: usize,
+    value: T,
+}
+
 impl<T> Node<T> {
     fn new(value: T) -> Self {
         Node {
@@ -150,9 +388,21 @@ impl<T> Node<T> {
             prev_sibling: None,
             next_sibling: None,
             children: None,
-            value: value,
+            value: Some(value),
         }
     }
+
+    fn value(&self) -> &T {
+        self.value.as_ref().expect("node has been removed")
+    }
+
+    fn value_mut(&mut self) -> &mut T {
+        self.value.as_mut().expect("node has been removed")
+    }
+
+    fn is_removed(&self) -> bool {
+        self.value.is_none()
+    }
 }
 
 impl<T> Tree<T> {
@@ -161,6 +411,21 @@ impl<T> Tree<T> {
         Tree {
             id: tree_id_seq_next(),
             vec: vec![Node::new(root)],
+            removed: 0,
+        }
+    }
+
+    /// Creates a new tree with a root node and the specified tree ID, instead of one drawn from
+    /// the global sequence.
+    ///
+    /// Node IDs are only checked against the ID of the tree they are used with, so trees sharing
+    /// an ID accept each other's node IDs. An ID chosen here may collide with one assigned
+    /// automatically to another tree.
+    pub fn new_with_id(root: T, id: usize) -> Self {
+        Tree {
+            id,
+            vec: vec![Node::new(root)],
+            removed: 0,
         }
     }
 
@@ -171,7 +436,109 @@ impl<T> Tree<T> {
         Tree {
             id: tree_id_seq_next(),
             vec: vec,
+            removed: 0,
+        }
+    }
+
+    /// Creates a new tree with a root node, reserving capacity for a complete tree with the
+    /// specified branching factor and depth.
+    ///
+    /// The estimate is `b^0 + b^1 + ... + b^depth`, saturating at `usize::MAX`. It is only a hint:
+    /// if the capacity cannot be reserved, none is.
+    pub fn with_estimated(root: T, branching_factor: usize, depth: usize) -> Self {
+        let estimate = match branching_factor {
+            0 => 1,
+            1 => depth.saturating_add(1),
+            _ => {
+                let mut estimate: usize = 0;
+                let mut level: usize = 1;
+                for _ in 0..depth.saturating_add(1) {
+                    estimate = estimate.saturating_add(level);
+                    if estimate == usize::MAX {
+                        break;
+                    }
+                    level = level.saturating_mul(branching_factor);
+                }
+                estimate
+            },
+        };
+
+        let mut vec = Vec::new();
+        let _ = vec.try_reserve(estimate);
+        vec.push(Node::new(root));
+        Tree {
+            id: tree_id_seq_next(),
+            vec,
+            removed: 0,
+        }
+    }
+
+    /// Creates a tree from `(value, parent)` rows, building as much of it as possible.
+    ///
+    /// A parent of `None` makes the row a child of `root`; `Some(i)` makes it a child of the row
+    /// at position `i`, which may come before or after it. Children are appended in row order.
+    ///
+    /// Rows whose parent cannot be resolved are left as orphans and reported as warnings.
+    pub fn build_lenient<I>(root: T, rows: I) -> (Self, Vec<BuildWarning>)
+        where I: IntoIterator<Item = (T, Option<usize>)> {
+        let mut tree = Tree::new(root);
+        let mut parents = Vec::new();
+        let mut ids = Vec::new();
+        for (value, parent) in rows {
+            ids.push(tree.orphan(value).id());
+            parents.push(parent);
+        }
+
+        let mut warnings = Vec::new();
+        for (row, parent) in parents.into_iter().enumerate() {
+            let parent_id = match parent {
+                None => tree.root().id(),
+                Some(parent) if parent >= ids.len() => {
+                    warnings.push(BuildWarning::MissingParent { row, parent });
+                    continue;
+                },
+                Some(parent) => {
+                    let parent_node = tree.get(ids[parent]);
+                    if parent == row || parent_node.ancestors().any(|n| n.id() == ids[row]) {
+                        warnings.push(BuildWarning::Cycle { row, parent });
+                        continue;
+                    }
+                    ids[parent]
+                },
+            };
+            // Cycles have been ruled out above.
+            let _ = unsafe { tree.get_mut(parent_id).append_id(ids[row]) };
+        }
+
+        (tree, warnings)
+    }
+
+    /// Creates a complete binary tree from values in level order.
+    ///
+    /// The children of the `i`th value are the `2i + 1`th and `2i + 2`th values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    pub fn balanced_binary_from<I>(values: I) -> Self where I: IntoIterator<Item = T> {
+        let mut values = values.into_iter();
+        let root = values.next().expect("cannot build a tree from no values");
+        let mut tree = Tree::with_capacity(root, values.size_hint().0 + 1);
+        for (i, value) in values.enumerate() {
+            let _ = tree.get_unchecked_mut(i / 2).append(value);
         }
+        tree
+    }
+
+    /// Returns the number of nodes in the tree, including orphans but not removed nodes.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.vec.len() - self.removed
+    }
+
+    /// Returns the number of nodes the tree can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
     }
 
     /// Returns a reference to the root node.
@@ -195,7 +562,7 @@ impl<T> Tree<T> {
     ///
     /// # Panics
     ///
-    /// Panics if `id` does not refer to a node in this tree.
+    /// Panics if `id` does not refer to a node in this tree, or refers to a removed node.
     pub fn get(&self, id: NodeId<T>) -> NodeRef<T> {
         self.get_unchecked(self.validate_id(id))
     }
@@ -204,14 +571,1126 @@ impl<T> Tree<T> {
     ///
     /// # Panics
     ///
-    /// Panics if `id` does not refer to a node in this tree.
+    /// Panics if `id` does not refer to a node in this tree, or refers to a removed node.
     pub fn get_mut(&mut self, id: NodeId<T>) -> NodeMut<T> {
         let index = self.validate_id(id);
         self.get_unchecked_mut(index)
     }
 
+    /// Decomposes the tree into its ID and its storage, one entry per slot in storage order.
+    ///
+    /// The root is at index 0. Removed nodes leave vacant slots with no value and no links.
+    pub fn into_parts(self) -> (usize, Vec<(Links, Option<T>)>) {
+        let nodes = self.vec.into_iter().map(|node| {
+            let links = Links {
+                parent: node.parent,
+                prev_sibling: node.prev_sibling,
+                next_sibling: node.next_sibling,
+                children: node.children,
+            };
+            (links, node.value)
+        }).collect();
+        (self.id, nodes)
+    }
+
+    /// Reassembles a tree from the parts returned by `into_parts`, returning the first
+    /// inconsistency found if they do not form a consistent tree.
+    ///
+    /// Node IDs created by a tree with the same ID are valid for the new tree, so reusing the ID
+    /// of a tree which is still in use defeats the check that IDs are only used with their own
+    /// tree.
+    pub fn from_parts(id: usize, nodes: Vec<(Links, Option<T>)>) -> Result<Self, Inconsistency> {
+        let fail = |node, kind| Err(Inconsistency { node, kind });
+        let len = nodes.len();
+        match nodes.first() {
+            None => return fail(0, InconsistencyKind::NoRoot),
+            Some(&(_, None)) => return fail(0, InconsistencyKind::RootRemoved),
+            Some(_) => { },
+        }
+
+        let mut vec = Vec::with_capacity(len);
+        let mut removed = 0;
+        for (index, (links, value)) in nodes.into_iter().enumerate() {
+            let children = links.children.map_or(Vec::new(), |(first, last)| vec![first, last]);
+            let in_range = links.parent.iter()
+                .chain(&links.prev_sibling)
+                .chain(&links.next_sibling)
+                .chain(&children)
+                .all(|&link| link < len);
+            if !in_range {
+                return fail(index, InconsistencyKind::LinkOutOfRange);
+            }
+            if value.is_none() {
+                if links != Links::default() {
+                    return fail(index, InconsistencyKind::RemovedWithLinks);
+                }
+                removed += 1;
+            }
+            vec.push(Node {
+                parent: links.parent,
+                prev_sibling: links.prev_sibling,
+                next_sibling: links.next_sibling,
+                children: links.children,
+                value,
+            });
+        }
+
+        let tree = Tree { id, vec, removed };
+        tree.sanity_check()?;
+        Ok(tree)
+    }
+
+    /// Returns a reference to the specified node, or an error if `id` does not refer to a node in
+    /// this tree.
+    pub fn try_validate(&self, id: NodeId<T>) -> Result<NodeRef<'_, T>, IdError> {
+        if id.tree_id != self.id {
+            return Err(IdError::WrongTree);
+        }
+        match self.vec.get(id.index) {
+            None => Err(IdError::OutOfRange),
+            Some(node) if node.is_removed() => Err(IdError::Removed),
+            Some(_) => Ok(self.get_unchecked(id.index)),
+        }
+    }
+
+    /// Returns the index of the specified node in the tree's storage, or `None` if `id` does not
+    /// refer to a node in this tree.
+    ///
+    /// This is the inverse of `get_by_index`.
+    pub fn index_of(&self, id: NodeId<T>) -> Option<usize> {
+        self.try_validate(id).ok().map(|node| node.index)
+    }
+
+    /// Returns a reference to the node at the specified index in the tree's storage, or `None` if
+    /// there is no node there.
+    pub fn get_by_index(&self, index: usize) -> Option<NodeRef<'_, T>> {
+        match self.vec.get(index) {
+            Some(node) if !node.is_removed() => Some(self.get_unchecked(index)),
+            _ => None,
+        }
+    }
+
+    /// Returns a map from each node ID to its index in a pre-order traversal from the root.
+    ///
+    /// The root is labelled 0. Orphans are not labelled.
+    pub fn label_pre_order(&self) -> HashMap<NodeId<T>, usize> {
+        self.pre_order_ids()
+            .into_iter()
+            .enumerate()
+            .map(|(label, id)| (id, label))
+            .collect()
+    }
+
+    /// Returns the node IDs in pre-order from the root, i.e. the inverse of `label_pre_order`.
+    pub fn pre_order_ids(&self) -> Vec<NodeId<T>> {
+        self.root()
+            .traverse()
+            .filter_map(|edge| match edge {
+                Edge::Open(node) => Some(node.id()),
+                Edge::Close(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over the root and the nodes at most `max_depth` levels below it, in
+    /// pre-order.
+    ///
+    /// Deeper subtrees are skipped, so a depth of 0 yields only the root.
+    pub fn descendants_within(&self, max_depth: usize) -> impl Iterator<Item = NodeRef<'_, T>> {
+        let root = self.root();
+        std::iter::once(root).chain(root.descendants_within(max_depth))
+    }
+
+    /// Returns an iterator over the values of the nodes reachable from the root, in post-order.
+    pub fn values_post_order(&self) -> impl Iterator<Item = &T> {
+        self.root().traverse().filter_map(|edge| match edge {
+            Edge::Open(_) => None,
+            Edge::Close(node) => Some(node.value()),
+        })
+    }
+
+    /// Returns the IDs of the nodes not reachable from the root, i.e. orphans and their
+    /// descendants, in the order they were created.
+    pub fn orphan_ids(&self) -> Vec<NodeId<T>> {
+        let mut reachable = vec![false; self.vec.len()];
+        for index in self.pre_order_indexes(0) {
+            reachable[index] = true;
+        }
+        self.nodes().filter(|node| !reachable[node.index]).map(|node| node.id()).collect()
+    }
+
+    /// Appends every orphan to the children of the specified node, in the order the orphans were
+    /// created, making the whole tree reachable from the root.
+    ///
+    /// Only orphans themselves are moved; their descendants come along with them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` does not refer to a node in this tree, or refers to a node that is not
+    /// reachable from the root.
+    pub fn attach_all_orphans(&mut self, parent: NodeId<T>) {
+        let parent = self.validate_id(parent);
+        let top = self.get_unchecked(parent).ancestors_inclusive().last().unwrap().index;
+        assert!(top == 0, "cannot attach orphans below an unreachable node");
+        let mut children: Vec<usize> = self.get_unchecked(parent)
+            .children()
+            .map(|child| child.index)
+            .collect();
+        children.extend((1..self.vec.len()).filter(|&index| {
+            let node = self.get_node_unchecked(index);
+            node.parent.is_none() && !node.is_removed()
+        }));
+        self.relink_children(parent, &children);
+    }
+
+    /// Rewrites the tree's storage so that the nodes reachable from the root are laid out in
+    /// pre-order, making traversals more cache-friendly, and returns a map from each old node ID
+    /// to its new ID.
+    ///
+    /// Orphans are laid out after the nodes reachable from the root and vacant slots are dropped.
+    /// All existing node IDs for the tree are invalidated.
+    pub fn rebuild_in_preorder(&mut self) -> HashMap<NodeId<T>, NodeId<T>> {
+        let old_id = self.id;
+        let order = self.relayout();
+        order
+            .into_iter()
+            .enumerate()
+            .map(|(new_index, old_index)| {
+                let id = NodeId { tree_id: old_id, index: old_index, marker: PhantomData };
+                (id, self.node_id(new_index))
+            })
+            .collect()
+    }
+
+    /// Returns a map from each number of children to the number of nodes, including orphans, with
+    /// that many children.
+    pub fn arity_histogram(&self) -> HashMap<usize, usize> {
+        let mut histogram = HashMap::new();
+        for node in self.nodes() {
+            *histogram.entry(node.children().count()).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Returns a map from each node ID, including those of orphans, to the number of nodes in its
+    /// subtree.
+    pub fn subtree_sizes(&self) -> HashMap<NodeId<T>, usize> {
+        let mut sizes = HashMap::with_capacity(self.len());
+        for root in self.nodes().filter(|node| node.parent().is_none()) {
+            let mut stack = vec![0];
+            for edge in root.traverse() {
+                match edge {
+                    Edge::Open(_) => stack.push(0),
+                    Edge::Close(node) => {
+                        let size = stack.pop().unwrap() + 1;
+                        *stack.last_mut().unwrap() += size;
+                        let _ = sizes.insert(node.id(), size);
+                    },
+                }
+            }
+        }
+        sizes
+    }
+
+    /// Returns the ID of the node, including orphans, for which `metric` is largest.
+    ///
+    /// `metric` is called once per node and can measure anything about the node's subtree, such
+    /// as its size. Ties go to the node created first.
+    pub fn largest_subtree_by<M, F>(&self, mut metric: F) -> NodeId<T>
+        where M: Ord, F: FnMut(NodeRef<T>) -> M {
+        self.nodes().min_by_key(|&node| Reverse(metric(node))).unwrap().id()
+    }
+
+    /// Returns the depth of the specified node, i.e. its number of ancestors.
+    ///
+    /// The root and other orphans have depth 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` does not refer to a node in this tree.
+    pub fn depth_of(&self, id: NodeId<T>) -> usize {
+        self.get(id).ancestors().count()
+    }
+
+    /// Returns an iterator over each pair of consecutive nodes in a pre-order traversal from the
+    /// root.
+    ///
+    /// Orphans are not included.
+    pub fn preorder_windows(&self) -> impl Iterator<Item = (NodeRef<'_, T>, NodeRef<'_, T>)> {
+        let root = self.root();
+        std::iter::once(root).chain(root.descendants()).zip(root.descendants())
+    }
+
+    /// Returns an iterator over the depth, ID and value of each node, including orphans, in the
+    /// order the nodes were created.
+    ///
+    /// Depths are computed up front in a single pass over the nodes.
+    pub fn iter_with_depth(&self) -> impl Iterator<Item = (usize, NodeId<T>, &T)> + '_ {
+        let mut depths: Vec<Option<usize>> = vec![None; self.vec.len()];
+        let mut path = Vec::new();
+        for start in 0..self.vec.len() {
+            let mut index = start;
+            let mut depth = loop {
+                if let Some(depth) = depths[index] {
+                    break depth;
+                }
+                match self.vec[index].parent {
+                    Some(parent) => {
+                        path.push(index);
+                        index = parent;
+                    },
+                    None => {
+                        depths[index] = Some(0);
+                        break 0;
+                    },
+                }
+            };
+            while let Some(index) = path.pop() {
+                depth += 1;
+                depths[index] = Some(depth);
+            }
+        }
+
+        self.nodes().map(move |node| (depths[node.index].unwrap(), node.id(), node.value()))
+    }
+
+    /// Returns the height of the specified node's subtree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` does not refer to a node in this tree.
+    pub fn height_of(&self, id: NodeId<T>) -> usize {
+        self.get(id).height()
+    }
+
+    /// Creates a tree of the same shape, including orphans, whose values are references to the
+    /// values of this tree.
+    pub fn as_ref(&self) -> Tree<&T> {
+        Tree {
+            id: tree_id_seq_next(),
+            vec: self.vec.iter().map(|node| {
+                Node {
+                    parent: node.parent,
+                    prev_sibling: node.prev_sibling,
+                    next_sibling: node.next_sibling,
+                    children: node.children,
+                    value: node.value.as_ref(),
+                }
+            }).collect(),
+            removed: self.removed,
+        }
+    }
+
+    /// Creates a tree of the same shape by mapping each value.
+    ///
+    /// Node IDs are preserved by index, including those of orphans.
+    pub fn map<U, F>(self, mut f: F) -> Tree<U> where F: FnMut(T) -> U {
+        self.map_indexed(|_, value| f(value))
+    }
+
+    /// Consumes the tree, converting it bottom-up by calling `make_node` with each value and the
+    /// converted children of its node, in order, and returns the root's result.
+    ///
+    /// Orphans are dropped.
+    pub fn fold_into_external<B, F>(mut self, mut make_node: F) -> B where F: FnMut(T, Vec<B>) -> B {
+        let post_order: Vec<usize> = self.root()
+            .traverse()
+            .filter_map(|edge| match edge {
+                Edge::Close(node) => Some(node.index),
+                Edge::Open(_) => None,
+            })
+            .collect();
+
+        let mut results: Vec<Option<B>> = self.vec.iter().map(|_| None).collect();
+        for index in post_order {
+            let mut children = Vec::new();
+            let mut next_child = self.vec[index].children.map(|(first, _)| first);
+            while let Some(child) = next_child {
+                children.push(results[child].take().unwrap());
+                next_child = self.vec[child].next_sibling;
+            }
+            let value = self.vec[index].value.take().unwrap();
+            results[index] = Some(make_node(value, children));
+        }
+        results[0].take().unwrap()
+    }
+
+    /// Creates a tree of the same shape by mapping each value along with its index in the tree.
+    ///
+    /// Node IDs are preserved by index, including those of orphans.
+    pub fn map_indexed<U, F>(self, mut f: F) -> Tree<U> where F: FnMut(usize, T) -> U {
+        Tree {
+            id: tree_id_seq_next(),
+            vec: self.vec.into_iter().enumerate().map(|(index, node)| {
+                Node {
+                    parent: node.parent,
+                    prev_sibling: node.prev_sibling,
+                    next_sibling: node.next_sibling,
+                    children: node.children,
+                    value: node.value.map(|value| f(index, value)),
+                }
+            }).collect(),
+            removed: self.removed,
+        }
+    }
+
+    /// Creates a tree of the same shape by mapping each value along with its path of child
+    /// positions from the root.
+    ///
+    /// The root's path is empty. Orphans are not mapped.
+    pub fn map_ref_with_path<U, F>(&self, mut f: F) -> Tree<U> where F: FnMut(&[usize], &T) -> U {
+        let root = self.root();
+        let mut tree = Tree::with_capacity(f(&[], root.value()), self.len());
+        let mut path = Vec::new();
+        let mut stack = vec![(0, 0)];
+
+        for edge in root.traverse().skip(1) {
+            match edge {
+                Edge::Open(node) => {
+                    let (parent, position) = {
+                        let top = stack.last_mut().unwrap();
+                        top.1 += 1;
+                        (top.0, top.1 - 1)
+                    };
+                    path.push(position);
+                    let value = f(&path, node.value());
+                    let index = tree.get_unchecked_mut(parent).append(value).index;
+                    stack.push((index, 0));
+                },
+                Edge::Close(_) => {
+                    let _ = stack.pop();
+                    let _ = path.pop();
+                },
+            }
+        }
+
+        tree
+    }
+
+    /// Creates a tree by mapping each value, dropping the subtree of every node for which `f`
+    /// returns `None`.
+    ///
+    /// Nodes in dropped subtrees are not mapped. Orphans are not mapped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns `None` for the root.
+    pub fn filter_map<U, F>(&self, mut f: F) -> Tree<U> where F: FnMut(&T) -> Option<U> {
+        let root = self.root();
+        let value = f(root.value()).expect("filter_map dropped the root");
+        let mut tree = Tree::with_capacity(value, self.len());
+        let mut stack: Vec<(NodeRef<T>, usize)> = root.children().rev().map(|c| (c, 0)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            if let Some(value) = f(node.value()) {
+                let index = tree.get_unchecked_mut(parent).append(value).index;
+                stack.extend(node.children().rev().map(|child| (child, index)));
+            }
+        }
+        tree
+    }
+
+    /// Creates a tree of the same shape whose values are states computed top-down, each from the
+    /// node's value and its parent's state.
+    ///
+    /// The root's state is computed from `root_state`. Orphans are not mapped.
+    pub fn traverse_inherited<S, F>(&self, root_state: S, mut f: F) -> Tree<S>
+        where F: FnMut(&T, &S) -> S {
+        let root = self.root();
+        let mut tree = Tree::with_capacity(f(root.value(), &root_state), self.len());
+        let mut stack = vec![0];
+
+        for edge in root.traverse().skip(1) {
+            match edge {
+                Edge::Open(node) => {
+                    let parent = *stack.last().unwrap();
+                    let state = f(node.value(), tree.get_node_unchecked(parent).value());
+                    let index = tree.get_unchecked_mut(parent).append(state).index;
+                    stack.push(index);
+                },
+                Edge::Close(_) => { let _ = stack.pop(); },
+            }
+        }
+
+        tree
+    }
+
+    /// Walks the tree from the root, calling the visitor on entering and leaving each node.
+    ///
+    /// Nodes are visited in the same order as `NodeRef::walk`. Orphans are not visited.
+    pub fn walk_mut<V: VisitorMut<T>>(&mut self, visitor: &mut V) {
+        let mut index = 0;
+        let mut depth = 0;
+        loop {
+            visitor.enter(self.node_id(index), depth, self.vec[index].value_mut());
+            if let Some((first, _)) = self.vec[index].children {
+                index = first;
+                depth += 1;
+                continue;
+            }
+            loop {
+                visitor.leave(self.node_id(index), depth, self.vec[index].value_mut());
+                if index == 0 {
+                    return;
+                }
+                match self.vec[index].next_sibling {
+                    Some(next) => {
+                        index = next;
+                        break;
+                    },
+                    None => {
+                        index = self.vec[index].parent.unwrap();
+                        depth -= 1;
+                    },
+                }
+            }
+        }
+    }
+
+    /// Returns the largest total weight of the nodes along a path from the root to a leaf.
+    ///
+    /// Totals saturate at `u64::MAX`.
+    pub fn weighted_height<W>(&self, mut weight: W) -> u64 where W: FnMut(&T) -> u64 {
+        let mut totals: Vec<u64> = Vec::new();
+        let mut max = 0;
+        for edge in self.root().traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    let total = totals.last().cloned().unwrap_or(0).saturating_add(weight(node.value()));
+                    totals.push(total);
+                },
+                Edge::Close(node) => {
+                    let total = totals.pop().unwrap();
+                    if !node.has_children() && total > max {
+                        max = total;
+                    }
+                },
+            }
+        }
+        max
+    }
+
+    /// Returns the number of leaves reachable from the root, which is also the number of paths
+    /// from the root to a leaf.
+    pub fn leaf_count(&self) -> usize {
+        self.root()
+            .traverse()
+            .filter(|edge| match *edge {
+                Edge::Open(node) => !node.has_children(),
+                Edge::Close(_) => false,
+            })
+            .count()
+    }
+
+    /// Returns the sum of the depths of the leaves reachable from the root, i.e. the external
+    /// path length.
+    pub fn total_path_length(&self) -> usize {
+        let mut depth = 0;
+        let mut total = 0;
+        for edge in self.root().traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    if !node.has_children() {
+                        total += depth;
+                    }
+                    depth += 1;
+                },
+                Edge::Close(_) => depth -= 1,
+            }
+        }
+        total
+    }
+
+    /// Returns true if every node reachable from the root has at most one child.
+    pub fn is_path(&self) -> bool {
+        let root = self.root();
+        Some(root).into_iter().chain(root.first_children()).all(|node| {
+            match node.node.children {
+                Some((first, last)) => first == last,
+                None => true,
+            }
+        })
+    }
+
+    /// Counts the nodes for which a predicate returns true, given each node's value and its
+    /// parent's value.
+    ///
+    /// All nodes, including orphans, are counted. The root and orphans are given `None` as their
+    /// parent's value.
+    pub fn count_where_with_parent<F>(&self, mut f: F) -> usize
+        where F: FnMut(&T, Option<&T>) -> bool {
+        self.nodes()
+            .filter(|node| f(node.value(), node.parent().map(|p| p.value())))
+            .count()
+    }
+
+    /// Pairs up the nodes of this tree and another tree of the same shape, in pre-order.
+    ///
+    /// Returns `None` if the trees differ in shape. Orphans are not considered.
+    pub fn structural_correspondence<U>(&self, other: &Tree<U>)
+        -> Option<Vec<(NodeId<T>, NodeId<U>)>> {
+        let mut pairs = Vec::new();
+        let mut ours = self.root().traverse();
+        let mut theirs = other.root().traverse();
+        loop {
+            match (ours.next(), theirs.next()) {
+                (None, None) => return Some(pairs),
+                (Some(Edge::Open(a)), Some(Edge::Open(b))) => pairs.push((a.id(), b.id())),
+                (Some(Edge::Close(_)), Some(Edge::Close(_))) => { },
+                _ => return None,
+            }
+        }
+    }
+
+    /// Compares the shapes of this tree and another, returning the first node in pre-order whose
+    /// number of children differs, or `None` if the shapes match.
+    ///
+    /// Orphans are not considered.
+    pub fn shape_mismatch<U>(&self, other: &Tree<U>) -> Option<ShapeMismatch> {
+        let mut stack = vec![(self.root(), other.root(), Vec::new())];
+        while let Some((ours, theirs, path)) = stack.pop() {
+            let expected_children = ours.children().count();
+            let actual_children = theirs.children().count();
+            if expected_children != actual_children {
+                return Some(ShapeMismatch { path, expected_children, actual_children });
+            }
+            let children: Vec<_> = ours.children().zip(theirs.children()).collect();
+            for (i, (a, b)) in children.into_iter().enumerate().rev() {
+                let mut child_path = path.clone();
+                child_path.push(i);
+                stack.push((a, b, child_path));
+            }
+        }
+        None
+    }
+
+    /// Returns the path of IDs from the root down to the lowest common ancestor of two nodes,
+    /// inclusive.
+    ///
+    /// A node counts as its own ancestor here, so the path for a node and its descendant ends at
+    /// the node. Returns an empty path if the nodes do not share a root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either ID does not refer to a node in this tree.
+    pub fn shared_ancestor_path(&self, a: NodeId<T>, b: NodeId<T>) -> Vec<NodeId<T>> {
+        let path_to = |id| {
+            let node = self.get(id);
+            let mut path: Vec<NodeId<T>> = node.ancestors().map(|n| n.id()).collect();
+            path.reverse();
+            path.push(id);
+            path
+        };
+        path_to(a)
+            .into_iter()
+            .zip(path_to(b))
+            .take_while(|&(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    }
+
+    /// Calls `f` with the ID and a mutable reference to the value of every node, including
+    /// orphans, in the order the nodes were created.
+    pub fn update_values<F>(&mut self, mut f: F) where F: FnMut(NodeId<T>, &mut T) {
+        let tree_id = self.id;
+        for (index, node) in self.vec.iter_mut().enumerate() {
+            if let Some(value) = node.value.as_mut() {
+                f(NodeId { tree_id, index, marker: PhantomData }, value);
+            }
+        }
+    }
+
+    /// Replaces the value of every leaf, including orphan leaves, with the result of `f`.
+    ///
+    /// Nodes with children are left untouched.
+    pub fn map_leaves<F: FnMut(&T) -> T>(&mut self, mut f: F) {
+        for node in &mut self.vec {
+            if node.children.is_none() {
+                if let Some(ref mut value) = node.value {
+                    *value = f(value);
+                }
+            }
+        }
+    }
+
+    /// Removes leaves reachable from the root whose values match a predicate, repeating until no
+    /// such leaf remains.
+    ///
+    /// A node whose children are all removed becomes a leaf and is itself removed if it matches.
+    /// The root is never removed.
+    pub fn prune_childless_matching<F>(&mut self, mut f: F) where F: FnMut(&T) -> bool {
+        let post_order: Vec<usize> = self.root()
+            .traverse()
+            .filter_map(|edge| match edge {
+                Edge::Close(node) => Some(node.index),
+                Edge::Open(_) => None,
+            })
+            .collect();
+
+        for index in post_order {
+            let prune = {
+                let node = self.get_node_unchecked(index);
+                index != 0 && node.children.is_none() && f(node.value())
+            };
+            if prune {
+                let _ = self.remove_subtree(index);
+            }
+        }
+    }
+
+    /// Detaches the children of every node for which `should_stop` returns true, making it a leaf.
+    ///
+    /// Nodes are visited top-down in pre-order, starting at the root, and the detached children
+    /// and their descendants are not visited.
+    pub fn cut_below<F>(&mut self, mut should_stop: F) where F: FnMut(&T) -> bool {
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let children: Vec<usize> = self.get_unchecked(index)
+                .children()
+                .map(|child| child.index)
+                .collect();
+            if should_stop(self.get_node_unchecked(index).value()) {
+                for child in children {
+                    self.get_unchecked_mut(child).detach();
+                }
+            } else {
+                stack.extend(children.into_iter().rev());
+            }
+        }
+    }
+
+    /// Detaches every subtree whose root value matches `f`, returning the IDs of the detached
+    /// roots in pre-order.
+    ///
+    /// The descendants of a matched node are not visited, so a match nested inside another stays
+    /// in the detached subtree. The root itself is never matched.
+    pub fn detach_matching<F>(&mut self, mut f: F) -> Vec<NodeId<T>> where F: FnMut(&T) -> bool {
+        let mut detached = Vec::new();
+        let mut stack: Vec<usize> = self.root().children().rev().map(|child| child.index).collect();
+        while let Some(index) = stack.pop() {
+            if f(self.get_node_unchecked(index).value()) {
+                self.get_unchecked_mut(index).detach();
+                detached.push(self.node_id(index));
+            } else {
+                stack.extend(self.get_unchecked(index).children().rev().map(|child| child.index));
+            }
+        }
+        detached
+    }
+
+    /// Removes every subtree whose root value matches `f`, returning each as a new tree, in
+    /// pre-order.
+    ///
+    /// This is the owning counterpart to `detach_matching`: matches are found the same way, and
+    /// the IDs of the removed nodes become invalid.
+    pub fn drain_matching<F>(&mut self, f: F) -> Vec<Tree<T>> where F: FnMut(&T) -> bool {
+        self.detach_matching(f)
+            .into_iter()
+            .map(|id| self.take_subtree(id.index))
+            .collect()
+    }
+
+    /// Merges adjacent leaf children throughout the tree, including among orphans' children.
+    ///
+    /// For each pair of adjacent leaves, `f` is called with the left value and the right value.
+    /// If it returns true, it is expected to have folded the right value into the left, and the
+    /// right node is removed; the left node is then compared with its new next sibling.
+    pub fn coalesce_leaves<F>(&mut self, mut f: F) where F: FnMut(&mut T, &T) -> bool {
+        for parent in 0..self.vec.len() {
+            if self.vec[parent].is_removed() {
+                continue;
+            }
+            let mut next_child = self.vec[parent].children.map(|(first, _)| first);
+            while let Some(left) = next_child {
+                next_child = self.vec[left].next_sibling;
+                let right = match next_child {
+                    Some(right) => right,
+                    None => break,
+                };
+                if self.vec[left].children.is_some() || self.vec[right].children.is_some() {
+                    continue;
+                }
+
+                let merged = {
+                    let (left_node, right_node) = if left < right {
+                        let (head, tail) = self.vec.split_at_mut(right);
+                        (&mut head[left], &tail[0])
+                    } else {
+                        let (head, tail) = self.vec.split_at_mut(left);
+                        (&mut tail[0], &head[right])
+                    };
+                    f(left_node.value_mut(), right_node.value())
+                };
+                if merged {
+                    let _ = self.remove_subtree(right);
+                    next_child = Some(left);
+                }
+            }
+        }
+    }
+
+    /// Returns true if this tree and `other` have exactly the same structure and `eq` returns true
+    /// for each pair of corresponding values.
+    ///
+    /// This is `==` with a custom value comparison.
+    pub fn eq_by<F>(&self, other: &Tree<T>, mut eq: F) -> bool where F: FnMut(&T, &T) -> bool {
+        self.vec.len() == other.vec.len() && self.vec.iter().zip(&other.vec).all(|(a, b)| {
+            a.parent == b.parent
+                && a.prev_sibling == b.prev_sibling
+                && a.next_sibling == b.next_sibling
+                && a.children == b.children
+                && match (&a.value, &b.value) {
+                    (Some(a), Some(b)) => eq(a, b),
+                    (None, None) => true,
+                    _ => false,
+                }
+        })
+    }
+
+    /// Exchanges the subtree of a node in this tree with the subtree of a node in another tree,
+    /// returning the new IDs of the moved subtrees' roots in this tree and the other tree.
+    ///
+    /// Each subtree takes the other's place among its siblings. The moved nodes are removed from
+    /// their original trees, so their old IDs become invalid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either ID does not refer to a node in its tree, or refers to a root.
+    pub fn swap_with(&mut self, self_id: NodeId<T>, other: &mut Tree<T>, other_id: NodeId<T>)
+        -> (NodeId<T>, NodeId<T>) {
+        let self_index = self.validate_id(self_id);
+        let other_index = other.validate_id(other_id);
+        assert!(self_index != 0 && other_index != 0, "cannot swap a root");
+
+        let self_place = self.place_of(self_index);
+        let other_place = other.place_of(other_index);
+        let ours = self.remove_subtree(self_index);
+        let theirs = other.remove_subtree(other_index);
+        (self.insert_removed(self_place, theirs), other.insert_removed(other_place, ours))
+    }
+
+    /// Checks that the links between nodes are consistent, returning the first inconsistency
+    /// found.
+    pub fn sanity_check(&self) -> Result<(), Inconsistency> {
+        let fail = |node, kind| Err(Inconsistency { node, kind });
+        let live = |index: usize| index < self.vec.len() && !self.vec[index].is_removed();
+        let mut linked = vec![false; self.vec.len()];
+        let mut linked_children = 0;
+
+        if self.vec[0].parent.is_some() {
+            return fail(0, InconsistencyKind::RootHasParent);
+        }
+        for (index, node) in self.vec.iter().enumerate() {
+            if node.is_removed() {
+                continue;
+            }
+            if node.parent.is_none() && (node.prev_sibling.is_some() || node.next_sibling.is_some()) {
+                return fail(index, InconsistencyKind::ParentlessWithSiblings);
+            }
+            if let Some((first, last)) = node.children {
+                let mut prev = None;
+                let mut next = Some(first);
+                while let Some(child_index) = next {
+                    if !live(child_index) {
+                        return fail(index, InconsistencyKind::RemovedChild);
+                    }
+                    let child = &self.vec[child_index];
+                    if child.parent != Some(index) {
+                        return fail(child_index, InconsistencyKind::WrongParent);
+                    }
+                    if child.prev_sibling != prev {
+                        return fail(child_index, InconsistencyKind::WrongPrevSibling);
+                    }
+                    linked_children += 1;
+                    if linked_children > self.vec.len() {
+                        return fail(index, InconsistencyKind::SiblingCycle);
+                    }
+                    linked[child_index] = true;
+                    prev = Some(child_index);
+                    next = child.next_sibling;
+                }
+                if prev != Some(last) {
+                    return fail(index, InconsistencyKind::WrongLastChild);
+                }
+            }
+        }
+
+        for (index, node) in self.vec.iter().enumerate() {
+            if !node.is_removed() && node.parent.is_some() && !linked[index] {
+                return fail(index, InconsistencyKind::MissingFromParent);
+            }
+        }
+        for index in 0..self.vec.len() {
+            if live(index) && self.get_unchecked(index).ancestors().nth(self.vec.len()).is_some() {
+                return fail(index, InconsistencyKind::ParentCycle);
+            }
+        }
+        Ok(())
+    }
+
+    /// Recomputes the first and last child of every node from the nodes whose parent it is.
+    ///
+    /// Parent links are taken as authoritative. The children of each node are ordered by
+    /// following their sibling links, and sibling links to nodes with a different parent are cut.
+    pub fn repair_children_ranges(&mut self) {
+        let len = self.vec.len();
+        let mut members = vec![Vec::new(); len];
+        for (index, node) in self.vec.iter().enumerate() {
+            if let Some(parent) = node.parent {
+                if !node.is_removed() && parent < len && !self.vec[parent].is_removed() {
+                    members[parent].push(index);
+                }
+            }
+        }
+
+        for (parent, members) in members.into_iter().enumerate() {
+            if self.vec[parent].is_removed() {
+                continue;
+            }
+            let children = self.sibling_order(&members);
+            self.relink_children(parent, &children);
+        }
+    }
+
+    // Orders nodes by following their sibling links, starting new runs where the links break.
+    fn sibling_order(&self, members: &[usize]) -> Vec<usize> {
+        let set: HashSet<usize> = members.iter().cloned().collect();
+        let mut placed = HashSet::with_capacity(members.len());
+        let mut order = Vec::with_capacity(members.len());
+
+        for &member in members {
+            if placed.contains(&member) {
+                continue;
+            }
+
+            let mut start = member;
+            let mut walked = HashSet::new();
+            let _ = walked.insert(member);
+            while let Some(prev) = self.vec[start].prev_sibling {
+                if set.contains(&prev) && !placed.contains(&prev) && walked.insert(prev) {
+                    start = prev;
+                } else {
+                    break;
+                }
+            }
+
+            let mut next = Some(start);
+            while let Some(index) = next {
+                if !set.contains(&index) || !placed.insert(index) {
+                    break;
+                }
+                order.push(index);
+                next = self.vec[index].next_sibling;
+            }
+        }
+
+        order
+    }
+
+    // Returns the parent and previous sibling of a node.
+    fn place_of(&self, index: usize) -> (Option<usize>, Option<usize>) {
+        let node = self.get_node_unchecked(index);
+        (node.parent, node.prev_sibling)
+    }
+
+    // Inserts removed nodes as a subtree at a place returned by `place_of`.
+    fn insert_removed(&mut self, place: (Option<usize>, Option<usize>), nodes: Vec<Removed<T>>)
+        -> NodeId<T> {
+        let mut nodes = nodes.into_iter();
+        let root = nodes.next().unwrap();
+        let index = self.orphan(root.value).index;
+        self.graft(index, nodes);
+        let id = self.node_id(index);
+
+        // The new subtree is an orphan, so linking it cannot create a cycle.
+        unsafe {
+            match place {
+                (_, Some(prev)) => { let _ = self.get_unchecked_mut(prev).insert_id_after(id); },
+                (Some(parent), None) => { let _ = self.get_unchecked_mut(parent).prepend_id(id); },
+                (None, None) => { },
+            }
+        }
+        id
+    }
+
+    /// Removes the subtree of the specified node, returning the IDs the removed nodes had along
+    /// with their values, in pre-order.
+    ///
+    /// The returned IDs are no longer valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` does not refer to a node in this tree, or refers to the root.
+    pub fn extract_subtree(&mut self, id: NodeId<T>) -> Vec<(NodeId<T>, T)> {
+        let index = self.validate_id(id);
+        assert!(index != 0, "cannot remove the root");
+        let ids: Vec<NodeId<T>> = self.pre_order_indexes(index)
+            .into_iter()
+            .map(|index| self.node_id(index))
+            .collect();
+        ids.into_iter().zip(self.remove_subtree(index)).map(|(id, node)| (id, node.value)).collect()
+    }
+
+    /// Appends a subtree to the children of a node, returning the ID of the subtree's root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` does not refer to a node in this tree.
+    pub fn attach_subtree(&mut self, parent: NodeId<T>, subtree: Subtree<T>) -> NodeId<T> {
+        let parent = self.validate_id(parent);
+        self.vec.reserve(subtree.nodes.len());
+        let mut nodes = subtree.nodes.into_iter();
+        let root = nodes.next().unwrap();
+        let index = self.get_unchecked_mut(parent).append(root.value).index;
+        self.graft(index, nodes);
+        self.node_id(index)
+    }
+
+    /// Cuts the subtrees of the specified nodes out of this tree, returning them as new trees in
+    /// the order of `ids`.
+    ///
+    /// Deeper cut points are cut first, so a cut point within the subtree of another cut point
+    /// ends up in its own tree and is excluded from the other's. The cut nodes are removed from
+    /// this tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any ID does not refer to a node in this tree, refers to the root, or is repeated.
+    /// All IDs are checked before anything is cut, so the tree is unchanged after a panic.
+    pub fn cut(&mut self, ids: &[NodeId<T>]) -> Vec<Tree<T>> {
+        let mut seen = HashSet::with_capacity(ids.len());
+        let mut order: Vec<(usize, usize, usize)> = ids.iter()
+            .enumerate()
+            .map(|(position, &id)| {
+                let index = self.validate_id(id);
+                assert!(index != 0, "cannot cut the root");
+                assert!(seen.insert(index), "cannot cut a node twice");
+                (position, index, self.get_unchecked(index).ancestors().count())
+            })
+            .collect();
+        order.sort_by_key(|&(_, _, depth)| Reverse(depth));
+
+        let mut trees: Vec<(usize, Tree<T>)> = order.into_iter()
+            .map(|(position, index, _)| (position, self.take_subtree(index)))
+            .collect();
+        trees.sort_by_key(|&(position, _)| position);
+        trees.into_iter().map(|(_, tree)| tree).collect()
+    }
+
+    // Moves the subtree of a node into a new tree, leaving its slots vacant.
+    fn take_subtree(&mut self, index: usize) -> Tree<T> {
+        let mut nodes = self.remove_subtree(index).into_iter();
+        let root = nodes.next().unwrap();
+        let mut tree = Tree::with_capacity(root.value, nodes.len() + 1);
+        tree.graft(0, nodes);
+        tree
+    }
+
+    // Detaches the subtree of a node and vacates its slots, returning the removed nodes in
+    // pre-order.
+    fn remove_subtree(&mut self, index: usize) -> Vec<Removed<T>> {
+        self.get_unchecked_mut(index).detach();
+        let indexes = self.pre_order_indexes(index);
+        let mut positions = HashMap::with_capacity(indexes.len());
+
+        let mut removed = Vec::with_capacity(indexes.len());
+        for (position, index) in indexes.into_iter().enumerate() {
+            let node = self.vacate(index);
+            let _ = positions.insert(index, position);
+            removed.push(Removed {
+                parent: node.parent.map_or(0, |parent| positions[&parent]),
+                value: node.value.unwrap(),
+            });
+        }
+        removed
+    }
+
+    // Appends removed nodes following the first one below `root`, which stands in for the first.
+    fn graft<I>(&mut self, root: usize, nodes: I) where I: Iterator<Item = Removed<T>> {
+        let mut indexes = vec![root];
+        for node in nodes {
+            let index = self.get_unchecked_mut(indexes[node.parent]).append(node.value).index;
+            indexes.push(index);
+        }
+    }
+
+    // Returns the indexes of the subtree of a node in pre-order.
+    fn pre_order_indexes(&self, index: usize) -> Vec<usize> {
+        self.get_unchecked(index)
+            .traverse()
+            .filter_map(|edge| match edge {
+                Edge::Open(node) => Some(node.index),
+                Edge::Close(_) => None,
+            })
+            .collect()
+    }
+
+    // Makes the given nodes the children of a node, in order, replacing its current children.
+    fn relink_children(&mut self, parent: usize, indexes: &[usize]) {
+        for (i, &index) in indexes.iter().enumerate() {
+            let child = self.get_node_unchecked_mut(index);
+            child.parent = Some(parent);
+            child.prev_sibling = if i == 0 { None } else { Some(indexes[i - 1]) };
+            child.next_sibling = indexes.get(i + 1).cloned();
+        }
+        self.get_node_unchecked_mut(parent).children = match (indexes.first(), indexes.last()) {
+            (Some(&first), Some(&last)) => Some((first, last)),
+            _ => None,
+        };
+    }
+
+    // Lays the nodes out in pre-order from the root, followed by orphans in creation order, drops
+    // vacant slots and gives the tree a new ID. Returns the old index of each node by new index.
+    fn relayout(&mut self) -> Vec<usize> {
+        let mut order = self.pre_order_indexes(0);
+        for index in 1..self.vec.len() {
+            let node = self.get_node_unchecked(index);
+            if node.parent.is_none() && !node.is_removed() {
+                order.extend(self.pre_order_indexes(index));
+            }
+        }
+
+        let mut new_indexes = vec![0; self.vec.len()];
+        for (new_index, &old_index) in order.iter().enumerate() {
+            new_indexes[old_index] = new_index;
+        }
+
+        let mut vec = Vec::with_capacity(order.len());
+        for &old_index in &order {
+            let node = self.vacate(old_index);
+            vec.push(Node {
+                parent: node.parent.map(|i| new_indexes[i]),
+                prev_sibling: node.prev_sibling.map(|i| new_indexes[i]),
+                next_sibling: node.next_sibling.map(|i| new_indexes[i]),
+                children: node.children.map(|(f, l)| (new_indexes[f], new_indexes[l])),
+                value: node.value,
+            });
+        }
+        self.vec = vec;
+        self.id = tree_id_seq_next();
+        self.removed = 0;
+        order
+    }
+
+    // Replaces a node with a vacant slot, returning the old node.
+    fn vacate(&mut self, index: usize) -> Node<T> {
+        let vacant = Node {
+            parent: None,
+            prev_sibling: None,
+            next_sibling: None,
+            children: None,
+            value: None,
+        };
+        self.removed += 1;
+        mem::replace(self.get_node_unchecked_mut(index), vacant)
+    }
+
     fn validate_id(&self, id: NodeId<T>) -> usize {
         assert_eq!(self.id, id.tree_id);
+        // Trees can share an ID, so the index is not necessarily in range.
+        match self.vec.get(id.index) {
+            Some(node) => assert!(!node.is_removed(), "node ID refers to a removed node"),
+            None => panic!("node ID is out of range"),
+        }
         id.index
     }
 
@@ -247,6 +1726,322 @@ impl<T> Tree<T> {
     }
 }
 
+impl<T: PartialEq> Tree<T> {
+    /// Returns true if any node, including orphans, has a value equal to `value`.
+    pub fn contains(&self, value: &T) -> bool {
+        self.values().any(|v| v == value)
+    }
+
+    /// Descends from the root by following, for each value in turn, the first child with an
+    /// equal value, returning the node reached.
+    ///
+    /// An empty path returns the root. Returns `None` if some step has no matching child.
+    pub fn find_by_value_path(&self, values: &[T]) -> Option<NodeRef<'_, T>> {
+        values.iter().try_fold(self.root(), |node, value| node.find_child(value))
+    }
+}
+
+impl<T: Hash + PartialEq> Tree<T> {
+    /// Groups the nodes whose subtrees are equal in shape and values, returning only groups with
+    /// more than one member.
+    ///
+    /// Groups and their members are ordered by pre-order from the root. Orphans are not included.
+    pub fn duplicate_subtrees(&self) -> Vec<Vec<NodeId<T>>> {
+        // Hash every subtree bottom-up, combining each child's hash into its parent's.
+        let mut hashes = vec![0; self.vec.len()];
+        let mut open: Vec<(DefaultHasher, usize)> = Vec::new();
+        for edge in self.root().traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    let mut hasher = DefaultHasher::new();
+                    node.value().hash(&mut hasher);
+                    open.push((hasher, 0));
+                },
+                Edge::Close(node) => {
+                    let (mut hasher, children) = open.pop().unwrap();
+                    hasher.write_usize(children);
+                    let hash = hasher.finish();
+                    hashes[node.index] = hash;
+                    if let Some((parent, siblings)) = open.last_mut() {
+                        parent.write_u64(hash);
+                        *siblings += 1;
+                    }
+                },
+            }
+        }
+
+        let mut groups: Vec<Vec<NodeRef<T>>> = Vec::new();
+        let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+        for edge in self.root().traverse() {
+            let node = match edge {
+                Edge::Open(node) => node,
+                Edge::Close(_) => continue,
+            };
+            let candidates = by_hash.entry(hashes[node.index]).or_default();
+            match candidates.iter().find(|&&group| subtrees_eq(groups[group][0], node)) {
+                Some(&group) => groups[group].push(node),
+                None => {
+                    candidates.push(groups.len());
+                    groups.push(vec![node]);
+                },
+            }
+        }
+        groups.into_iter()
+            .filter(|group| group.len() > 1)
+            .map(|group| group.into_iter().map(|node| node.id()).collect())
+            .collect()
+    }
+}
+
+impl<T: Hash + PartialEq> Tree<T> {
+    /// Keeps the first occurrence in pre-order of each group of equal subtrees and turns each
+    /// later occurrence into a leaf whose value is `replace` called with the first occurrence's
+    /// ID.
+    ///
+    /// The descendants of the replaced nodes are removed, so their IDs become invalid; the
+    /// replaced nodes keep their IDs. Orphans are not considered.
+    pub fn deduplicate_subtrees<F>(&mut self, mut replace: F) where F: FnMut(NodeId<T>) -> T {
+        for group in self.duplicate_subtrees() {
+            let first = group[0];
+            for id in &group[1..] {
+                if self.get_node_unchecked(id.index).is_removed() {
+                    continue;
+                }
+                let children: Vec<usize> = self.get_unchecked(id.index)
+                    .children()
+                    .map(|child| child.index)
+                    .collect();
+                for child in children {
+                    let _ = self.remove_subtree(child);
+                }
+                *self.get_node_unchecked_mut(id.index).value_mut() = replace(first);
+            }
+        }
+    }
+}
+
+// Returns true if two subtrees are equal in shape and values.
+fn subtrees_eq<T: PartialEq>(a: NodeRef<T>, b: NodeRef<T>) -> bool {
+    fn key<T>(edge: Edge<'_, T>) -> (bool, &T) {
+        match edge {
+            Edge::Open(node) => (true, node.value()),
+            Edge::Close(node) => (false, node.value()),
+        }
+    }
+    a.traverse().map(key).eq(b.traverse().map(key))
+}
+
+impl<T: From<usize>> Tree<T> {
+    /// Replaces the value of each node with its index in a pre-order traversal from the root.
+    ///
+    /// The root gets 0. Orphans are left unchanged.
+    pub fn enumerate_values(&mut self) {
+        for (label, index) in self.pre_order_indexes(0).into_iter().enumerate() {
+            *self.get_node_unchecked_mut(index).value_mut() = T::from(label);
+        }
+    }
+}
+
+impl<T: Eq + Hash> Tree<T> {
+    /// Returns the most common value among the nodes, including orphans.
+    ///
+    /// Ties are broken in favour of the value whose first node comes first in the tree's storage.
+    pub fn mode(&self) -> Option<&T> {
+        let mut counts = HashMap::new();
+        for value in self.values() {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+        let mut mode = None;
+        for value in self.values() {
+            let count = counts[value];
+            if mode.map_or(0, |(_, max)| max) < count {
+                mode = Some((value, count));
+            }
+        }
+        mode.map(|(value, _)| value)
+    }
+}
+
+impl<T: Eq + Hash + Clone> Tree<T> {
+    /// Counts the number of nodes, including orphans, holding each value.
+    pub fn value_counts(&self) -> HashMap<T, usize> {
+        let mut counts = HashMap::new();
+        for value in self.values() {
+            *counts.entry(value.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+impl<T: Copy + Sum> Tree<T> {
+    /// Returns the sum of the values of all nodes, including orphans.
+    pub fn sum(&self) -> T {
+        self.values().cloned().sum()
+    }
+}
+
+impl<T: Ord> Tree<T> {
+    /// Returns the smallest value among all nodes, including orphans.
+    pub fn min_value(&self) -> &T {
+        self.values().min().unwrap()
+    }
+
+    /// Returns the largest value among all nodes, including orphans.
+    pub fn max_value(&self) -> &T {
+        self.values().max().unwrap()
+    }
+
+    /// Sorts the children of every node and lays the nodes out in pre-order, so that trees which
+    /// differ only in the order of siblings compare equal.
+    ///
+    /// Children are sorted bottom-up by value, and siblings with equal values by the contents of
+    /// their already sorted subtrees. Orphans are laid out after the nodes reachable from the
+    /// root. All existing node IDs for the tree are invalidated.
+    pub fn canonicalize(&mut self) {
+        let post_order: Vec<usize> = (0..self.vec.len())
+            .filter(|&index| {
+                let node = self.get_node_unchecked(index);
+                node.parent.is_none() && !node.is_removed()
+            })
+            .flat_map(|root| {
+                self.get_unchecked(root)
+                    .traverse()
+                    .filter_map(|edge| match edge {
+                        Edge::Open(_) => None,
+                        Edge::Close(node) => Some(node.index),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for index in post_order {
+            let mut children: Vec<usize> = self.get_unchecked(index)
+                .children()
+                .map(|child| child.index)
+                .collect();
+            children.sort_by(|&a, &b| {
+                canonical_key(self.get_unchecked(a)).cmp(canonical_key(self.get_unchecked(b)))
+            });
+            self.relink_children(index, &children);
+        }
+        let _ = self.relayout();
+    }
+}
+
+// Returns the pre-order traversal of a subtree as a comparable sequence, with `None` closing each
+// node, so that subtrees compare by their values and shape.
+fn canonical_key<'a, T: 'a>(node: NodeRef<'a, T>) -> impl Iterator<Item = Option<&'a T>> {
+    node.traverse().map(|edge| match edge {
+        Edge::Open(node) => Some(node.value()),
+        Edge::Close(_) => None,
+    })
+}
+
+impl<T: Clone> Tree<T> {
+    /// Returns the part of this tree which `other` shares with it from the root down, or `None` if
+    /// the roots differ.
+    ///
+    /// Two matched nodes' children are paired off in order, and pairs are matched for as long as
+    /// their values are equal; the first unequal pair ends the matching among those children.
+    /// Orphans are not considered.
+    pub fn intersection(&self, other: &Tree<T>) -> Option<Tree<T>> where T: PartialEq {
+        if self.root().value() != other.root().value() {
+            return None;
+        }
+        let mut tree = Tree::new(self.root().value().clone());
+        let mut stack = vec![(self.root(), other.root(), 0)];
+        while let Some((a, b, index)) = stack.pop() {
+            let pairs = a.children()
+                .zip(b.children())
+                .take_while(|&(a, b)| a.value() == b.value());
+            for (a, b) in pairs {
+                let child = tree.get_unchecked_mut(index).append(a.value().clone()).index;
+                stack.push((a, b, child));
+            }
+        }
+        Some(tree)
+    }
+
+    /// Clones the subtrees of the specified nodes into new trees, in the order of `ids`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any ID does not refer to a node in this tree.
+    pub fn clone_subtrees(&self, ids: &[NodeId<T>]) -> Vec<Tree<T>> {
+        let mut stack = Vec::new();
+        ids.iter().map(|&id| {
+            let root = self.get(id);
+            let mut tree = Tree::new(root.value().clone());
+            for edge in root.traverse().skip(1) {
+                match edge {
+                    Edge::Open(node) => {
+                        let parent = stack.last().cloned().unwrap_or(0);
+                        let index = tree.get_unchecked_mut(parent)
+                            .append(node.value().clone())
+                            .index;
+                        stack.push(index);
+                    },
+                    Edge::Close(node) if node != root => { let _ = stack.pop(); },
+                    Edge::Close(_) => { },
+                }
+            }
+            tree
+        }).collect()
+    }
+}
+
+impl<T> Subtree<T> {
+    /// Creates a new subtree with a root node.
+    pub fn new(root: T) -> Self {
+        Subtree { nodes: vec![Removed { parent: 0, value: root }] }
+    }
+
+    /// Appends a new child to a node, returning the position of the new node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not the position of a node in this subtree.
+    pub fn append(&mut self, parent: usize, value: T) -> usize {
+        assert!(parent < self.nodes.len(), "parent position out of bounds");
+        self.nodes.push(Removed { parent, value });
+        self.nodes.len() - 1
+    }
+
+    /// Appends new leaf children to a node, in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not the position of a node in this subtree.
+    pub fn append_all<I>(&mut self, parent: usize, values: I) where I: IntoIterator<Item = T> {
+        for value in values {
+            let _ = self.append(parent, value);
+        }
+    }
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let description = match self.kind {
+            InconsistencyKind::NoRoot => "there is no root",
+            InconsistencyKind::RootRemoved => "the root has been removed",
+            InconsistencyKind::RootHasParent => "the root has a parent",
+            InconsistencyKind::LinkOutOfRange => "a link is out of range",
+            InconsistencyKind::RemovedWithLinks => "the removed node has links",
+            InconsistencyKind::RemovedChild => "a child has been removed",
+            InconsistencyKind::WrongParent => "the parent link does not match",
+            InconsistencyKind::WrongPrevSibling => "the previous sibling link does not match",
+            InconsistencyKind::SiblingCycle => "the children's sibling links form a cycle",
+            InconsistencyKind::WrongLastChild => "the last child link does not match",
+            InconsistencyKind::MissingFromParent => "the node is missing from its parent's children",
+            InconsistencyKind::ParentlessWithSiblings => "the node has siblings but no parent",
+            InconsistencyKind::ParentCycle => "the node is its own ancestor",
+        };
+        write!(f, "node {}: {}", self.node, description)
+    }
+}
+
+impl Error for Inconsistency { }
+
 impl<T: Default> Default for Tree<T> {
     fn default() -> Self {
         Tree::new(T::default())
@@ -258,14 +2053,31 @@ impl<T: Clone> Clone for Tree<T> {
         Tree {
             id: tree_id_seq_next(),
             vec: self.vec.clone(),
+            removed: self.removed,
         }
     }
 }
 
 impl<T: Eq> Eq for Tree<T> { }
+/// Trees are compared by content: the subtree of the root, then the subtree of each orphan in the
+/// order the orphans were created, by values and shape.
+///
+/// Vacant slots and the layout of the nodes in storage do not affect equality.
 impl<T: PartialEq> PartialEq for Tree<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.vec == other.vec
+        let roots = |tree: &Tree<T>| -> Vec<usize> {
+            (0..tree.vec.len())
+                .filter(|&index| {
+                    let node = tree.get_node_unchecked(index);
+                    node.parent.is_none() && !node.is_removed()
+                })
+                .collect()
+        };
+        let (ours, theirs) = (roots(self), roots(other));
+        ours.len() == theirs.len()
+            && ours.into_iter().zip(theirs).all(|(a, b)| {
+                subtrees_eq(self.get_unchecked(a), other.get_unchecked(b))
+            })
     }
 }
 
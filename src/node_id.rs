@@ -1,9 +1,20 @@
 #![allow(expl_impl_clone_on_copy)]
 
-use super::NodeId;
+use super::{NodeId, IdError};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::hash::Hash;
 use std::hash::Hasher;
 
+impl<T> NodeId<T> {
+    /// Returns the ID of the tree which created this node ID.
+    ///
+    /// Intended for debugging: IDs from the same tree share a tree ID.
+    pub fn tree_id(&self) -> usize {
+        self.tree_id
+    }
+}
+
 impl<T> Hash for NodeId<T> {
 	fn hash<H>(&self, state: &mut H) where H: Hasher {
 		state.write_usize(self.tree_id);
@@ -22,3 +33,15 @@ impl<T> PartialEq for NodeId<T> {
         self.tree_id == other.tree_id && self.index == other.index
     }
 }
+
+impl Display for IdError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            IdError::WrongTree => "node ID belongs to a different tree",
+            IdError::OutOfRange => "node ID is out of range",
+            IdError::Removed => "node ID refers to a removed node",
+        })
+    }
+}
+
+impl Error for IdError { }
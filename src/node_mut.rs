@@ -1,4 +1,6 @@
-use super::{NodeMut, Node, NodeId, NodeRef};
+use std::cmp::Ordering;
+
+use super::{Tree, NodeMut, Node, NodeId, NodeRef, Position};
 
 impl<'a, T: 'a> NodeMut<'a, T> {
     fn node(&self) -> &Node<T> {
@@ -11,7 +13,7 @@ impl<'a, T: 'a> NodeMut<'a, T> {
 
     /// Returns the value of this node.
     pub fn value(&mut self) -> &mut T {
-        &mut self.node_mut().value
+        self.node_mut().value_mut()
     }
 
     /// Returns the ID of this node.
@@ -66,12 +68,69 @@ impl<'a, T: 'a> NodeMut<'a, T> {
         self.append_unchecked(index)
     }
 
+    /// Appends a new node to this node's children and passes a mutator of it to `f`, returning
+    /// the ID of the new node.
+    pub fn append_with<F>(&mut self, value: T, f: F) -> NodeId<T> where F: FnOnce(&mut NodeMut<T>) {
+        let mut child = self.append(value);
+        f(&mut child);
+        child.id()
+    }
+
     /// Prepends a new node to this node's children, returning a mutator of the new node.
     pub fn prepend(&mut self, value: T) -> NodeMut<T> {
         let index = self.tree.orphan(value).index;
         self.prepend_unchecked(index)
     }
 
+    /// Appends the nodes of another tree reachable from its root to this node's children,
+    /// returning a mutator of the former root.
+    ///
+    /// Capacity for all of the nodes is reserved up front.
+    pub fn append_subtree(&mut self, mut other: Tree<T>) -> NodeMut<'_, T> {
+        let mut nodes = other.remove_subtree(0).into_iter();
+        self.tree.vec.reserve(nodes.len());
+        let root = nodes.next().unwrap();
+        let index = self.append(root.value).index;
+        self.tree.graft(index, nodes);
+        self.tree.get_unchecked_mut(index)
+    }
+
+    /// Moves the subtree of a node in another tree to this node's children, returning a mutator
+    /// of the moved node.
+    ///
+    /// The moved nodes' slots in `src` are left vacant, so their IDs become invalid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src_id` does not refer to a node in `src`, or refers to its root.
+    pub fn append_moved(&mut self, src: &mut Tree<T>, src_id: NodeId<T>) -> NodeMut<'_, T> {
+        let src_index = src.validate_id(src_id);
+        assert!(src_index != 0, "cannot remove the root");
+        let mut nodes = src.remove_subtree(src_index).into_iter();
+        self.tree.vec.reserve(nodes.len());
+        let root = nodes.next().unwrap();
+        let index = self.append(root.value).index;
+        self.tree.graft(index, nodes);
+        self.tree.get_unchecked_mut(index)
+    }
+
+    /// Inserts new children starting at the specified position among this node's children,
+    /// shifting the children after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of children.
+    pub fn insert_children_at<I>(&mut self, index: usize, values: I)
+        where I: IntoIterator<Item = T> {
+        let mut children = self.child_indexes();
+        assert!(index <= children.len(), "child position out of bounds");
+        let new_children: Vec<usize> = values.into_iter()
+            .map(|value| self.tree.orphan(value).index)
+            .collect();
+        let _ = children.splice(index..index, new_children);
+        self.relink_children(&children);
+    }
+
     /// Inserts a new sibling before this node, returning a mutator of the new node.
     ///
     /// # Panics
@@ -101,6 +160,152 @@ impl<'a, T: 'a> NodeMut<'a, T> {
         self.detach_unchecked();
     }
 
+    /// Detaches this node, moving its children into its place among its siblings, in order.
+    ///
+    /// A node without children is simply detached. If this node is an orphan, its children become
+    /// orphans.
+    pub fn splice_out(&mut self) {
+        let children = self.child_indexes();
+        let parent = match self.node().parent {
+            Some(parent) => parent,
+            None => {
+                for child in children {
+                    self.tree.get_unchecked_mut(child).detach();
+                }
+                return;
+            },
+        };
+
+        let mut siblings = self.tree.get_unchecked_mut(parent).child_indexes();
+        let position = siblings.iter().position(|&i| i == self.index).unwrap();
+        let _ = siblings.splice(position..position + 1, children);
+        self.tree.relink_children(parent, &siblings);
+
+        let node = self.node_mut();
+        node.parent = None;
+        node.prev_sibling = None;
+        node.next_sibling = None;
+        node.children = None;
+    }
+
+    /// Merges every node in the subtree starting at this node that has exactly one child with
+    /// that child, combining the parent's and child's values with `combine`.
+    ///
+    /// The child's children take its place, and a node is merged repeatedly until it no longer
+    /// has exactly one child. The merged children are removed, so their IDs become invalid.
+    pub fn collapse_single_child_chains<F>(&mut self, mut combine: F) where F: FnMut(T, T) -> T {
+        let mut stack = vec![self.index];
+        while let Some(index) = stack.pop() {
+            loop {
+                let child = match self.tree.get_node_unchecked(index).children {
+                    Some((first, last)) if first == last => first,
+                    _ => break,
+                };
+                let grandchildren = self.tree.get_unchecked_mut(child).child_indexes();
+                self.tree.relink_children(index, &grandchildren);
+                let child = self.tree.vacate(child);
+                let node = self.tree.get_node_unchecked_mut(index);
+                let value = node.value.take().unwrap();
+                node.value = Some(combine(value, child.value.unwrap()));
+            }
+            stack.extend(self.tree.get_unchecked_mut(index).child_indexes());
+        }
+    }
+
+    /// Detaches this node and attaches it to the children of `new_parent` at `position`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this node is the root, if `new_parent` does not refer to a node in this tree, if
+    /// it is this node or one of its descendants, or if the sibling given by `position` is this
+    /// node or is not a child of `new_parent`.
+    pub fn reattach_to(&mut self, new_parent: NodeId<T>, position: Position<T>) {
+        assert!(self.index != 0, "cannot reattach the root");
+        let parent = self.tree.validate_id(new_parent);
+        let id = self.id();
+        assert!(
+            parent != self.index && !self.tree.get_unchecked(parent).is_descendant_of(id),
+            "cannot reattach a node below itself"
+        );
+        let target = match position {
+            Position::Before(sibling) | Position::After(sibling) => {
+                let index = self.tree.validate_id(sibling);
+                assert!(index != self.index, "cannot position a node relative to itself");
+                assert_eq!(
+                    Some(parent),
+                    self.tree.get_node_unchecked(index).parent,
+                    "sibling is not a child of the new parent"
+                );
+                index
+            },
+            Position::First | Position::Last => parent,
+        };
+
+        self.detach();
+
+        // The new parent was checked not to be in this node's subtree, so no cycle can occur.
+        unsafe {
+            let mut target = self.tree.get_unchecked_mut(target);
+            let _ = match position {
+                Position::First => target.prepend_id(id),
+                Position::Last => target.append_id(id),
+                Position::Before(_) => target.insert_id_before(id),
+                Position::After(_) => target.insert_id_after(id),
+            };
+        }
+    }
+
+    /// Removes this node and its descendants from the tree, returning their values in pre-order.
+    ///
+    /// The IDs of the removed nodes become invalid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this node is the root.
+    pub fn drain_subtree(self) -> Vec<T> {
+        assert!(self.index != 0, "cannot remove the root");
+        self.tree.remove_subtree(self.index).into_iter().map(|node| node.value).collect()
+    }
+
+    /// Removes this node alone from the tree, moving its children into its place among its
+    /// siblings, and returns its value along with the IDs of the moved children.
+    ///
+    /// The ID of this node becomes invalid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this node is the root.
+    pub fn isolate(mut self) -> (T, Vec<NodeId<T>>) {
+        assert!(self.index != 0, "cannot remove the root");
+        let children = self.child_indexes().into_iter().map(|i| self.tree.node_id(i)).collect();
+        self.splice_out();
+        let node = self.tree.vacate(self.index);
+        (node.value.unwrap(), children)
+    }
+
+    /// Removes a child of this node by ID, along with its descendants, and returns its value.
+    ///
+    /// Like `Vec::swap_remove`, the order of the children is not preserved: the last child is
+    /// moved into the removed child's position. The IDs of the removed nodes become invalid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` does not refer to a child of this node.
+    pub fn swap_remove_child(&mut self, id: NodeId<T>) -> T {
+        let index = self.tree.validate_id(id);
+        assert_eq!(
+            self.tree.get_node_unchecked(index).parent,
+            Some(self.index),
+            "node is not a child of this node"
+        );
+        let last_index = self.node().children.unwrap().1;
+        if last_index != index {
+            self.tree.get_unchecked_mut(last_index).detach();
+            let _ = self.tree.get_unchecked_mut(index).insert_before_unchecked(last_index);
+        }
+        self.tree.remove_subtree(index).swap_remove(0).value
+    }
+
     /// Appends a node to this node's children by ID, returning a mutator of the referenced node.
     ///
     /// May cause cycles, which can cause unsafety in other operations.
@@ -177,6 +382,103 @@ impl<'a, T: 'a> NodeMut<'a, T> {
         self.reparent_prepend_unchecked(index);
     }
 
+    /// Sorts this node's children with a comparator function.
+    ///
+    /// The sort is stable.
+    pub fn sort_children_by<F>(&mut self, mut compare: F) where F: FnMut(&T, &T) -> Ordering {
+        let mut children = self.child_indexes();
+        {
+            let tree = &*self.tree;
+            children.sort_by(|&a, &b| {
+                compare(tree.get_node_unchecked(a).value(), tree.get_node_unchecked(b).value())
+            });
+        }
+        self.relink_children(&children);
+    }
+
+    /// Sorts this node's children with a key extraction function, computing each child's key
+    /// only once.
+    ///
+    /// The sort is stable.
+    pub fn sort_children_by_cached_key<K, F>(&mut self, mut f: F)
+        where K: Ord, F: FnMut(&T) -> K {
+        let mut children = self.child_indexes();
+        {
+            let tree = &*self.tree;
+            children.sort_by_cached_key(|&i| f(tree.get_node_unchecked(i).value()));
+        }
+        self.relink_children(&children);
+    }
+
+    /// Sorts the children of this node and of each of its descendants with a comparator
+    /// function.
+    pub fn sort_descendants_by<F>(&mut self, compare: F)
+        where F: FnMut(&T, &T) -> Ordering + Copy {
+        for index in self.tree.pre_order_indexes(self.index) {
+            self.tree.get_unchecked_mut(index).sort_children_by(compare);
+        }
+    }
+
+    /// Rearranges this node's children into the order given by `order`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is not a permutation of this node's children.
+    pub fn reorder_children(&mut self, order: &[NodeId<T>]) {
+        const NOT_PERMUTATION: &str = "order is not a permutation of the children";
+        let indexes: Vec<usize> = order.iter()
+            .map(|&id| self.tree.index_of(id).expect(NOT_PERMUTATION))
+            .collect();
+        let mut children = self.child_indexes();
+        let mut sorted = indexes.clone();
+        children.sort_unstable();
+        sorted.sort_unstable();
+        assert!(children == sorted, "{}", NOT_PERMUTATION);
+        self.relink_children(&indexes);
+    }
+
+    /// Replaces this node's children with two new nodes, moving the children for which `f`
+    /// returns true under the first and the rest under the second, preserving their order.
+    pub fn partition_children_by<F>(&mut self, mut f: F, true_label: T, false_label: T)
+        where F: FnMut(&T) -> bool {
+        let (matching, rest): (Vec<usize>, Vec<usize>) = {
+            let tree = &*self.tree;
+            self.child_indexes()
+                .into_iter()
+                .partition(|&i| f(tree.get_node_unchecked(i).value()))
+        };
+
+        let true_index = self.tree.orphan(true_label).index;
+        let false_index = self.tree.orphan(false_label).index;
+        self.tree.get_unchecked_mut(true_index).relink_children(&matching);
+        self.tree.get_unchecked_mut(false_index).relink_children(&rest);
+        self.relink_children(&[true_index, false_index]);
+    }
+
+    /// Detaches the children of this node for which `f` returns false, keeping the rest in order.
+    pub fn retain_children<F>(&mut self, mut f: F) where F: FnMut(&T) -> bool {
+        for index in self.child_indexes() {
+            if !f(self.tree.get_node_unchecked(index).value()) {
+                self.tree.get_unchecked_mut(index).detach();
+            }
+        }
+    }
+
+    fn child_indexes(&self) -> Vec<usize> {
+        let mut indexes = Vec::new();
+        let mut next_child = self.node().children.map(|t| t.0);
+        while let Some(index) = next_child {
+            indexes.push(index);
+            next_child = self.tree.get_node_unchecked(index).next_sibling;
+        }
+        indexes
+    }
+
+    // Makes the given nodes the children of this node, in order, replacing its current children.
+    fn relink_children(&mut self, indexes: &[usize]) {
+        self.tree.relink_children(self.index, indexes);
+    }
+
     fn append_unchecked(&mut self, new_child_index: usize) -> NodeMut<T> {
         let last_child_index = self.node().children.map(|t| t.1);
 
@@ -380,8 +682,107 @@ impl<'a, T: 'a> NodeMut<'a, T> {
     }
 }
 
+impl<'a, T: 'a + Clone> NodeMut<'a, T> {
+    /// Appends clones of the values in a slice to this node's children, in order.
+    pub fn append_slice(&mut self, values: &[T]) {
+        self.tree.vec.reserve(values.len());
+        for value in values {
+            let _ = self.append(value.clone());
+        }
+    }
+
+    /// Inserts clones of the values in a slice as new children starting at the specified
+    /// position among this node's children, shifting the children after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of children.
+    pub fn splice_children(&mut self, index: usize, values: &[T]) {
+        self.tree.vec.reserve(values.len());
+        self.insert_children_at(index, values.iter().cloned());
+    }
+
+    /// Inserts a deep clone of this node's subtree as its next sibling, returning the ID of the
+    /// copy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this node is an orphan.
+    pub fn duplicate_subtree(&mut self) -> NodeId<T> {
+        assert!(self.node().parent.is_some());
+        let value = self.node().value().clone();
+        let copy_index = self.tree.orphan(value).index;
+
+        let mut stack = vec![(self.index, copy_index)];
+        while let Some((source_index, target_index)) = stack.pop() {
+            let mut next_child = self.tree.get_node_unchecked(source_index).children.map(|t| t.0);
+            while let Some(child_index) = next_child {
+                let (value, next_sibling) = {
+                    let child = self.tree.get_node_unchecked(child_index);
+                    (child.value().clone(), child.next_sibling)
+                };
+                let child_copy_index = self.tree.get_unchecked_mut(target_index).append(value).index;
+                stack.push((child_index, child_copy_index));
+                next_child = next_sibling;
+            }
+        }
+
+        self.insert_after_unchecked(copy_index).id()
+    }
+
+    /// Overwrites the value of this node and each of its descendants with a clone of `value`.
+    pub fn fill_subtree(&mut self, value: T) {
+        for index in self.tree.pre_order_indexes(self.index) {
+            *self.tree.get_node_unchecked_mut(index).value_mut() = value.clone();
+        }
+    }
+
+    /// Rearranges this node's children so that their values match `desired`, in order.
+    ///
+    /// Each desired value is matched with the first unused child with an equal value, which keeps
+    /// its subtree and ID. Values without a match are appended as new leaves with a clone of the
+    /// value, and children left unmatched are detached.
+    pub fn reconcile_children_by_value(&mut self, desired: &[T]) where T: PartialEq {
+        let mut unused: Vec<Option<usize>> = self.child_indexes().into_iter().map(Some).collect();
+        let mut order = Vec::with_capacity(desired.len());
+        for value in desired {
+            let tree = &*self.tree;
+            let matching = unused.iter_mut().find(|index| match **index {
+                Some(index) => tree.get_node_unchecked(index).value() == value,
+                None => false,
+            });
+            match matching {
+                Some(index) => order.push(index.take().unwrap()),
+                None => order.push(self.tree.orphan(value.clone()).index),
+            }
+        }
+        for index in unused.into_iter().flatten() {
+            self.tree.get_unchecked_mut(index).detach();
+        }
+        self.relink_children(&order);
+    }
+}
+
 impl<'a, T: 'a> Into<NodeRef<'a, T>> for NodeMut<'a, T> {
     fn into(self) -> NodeRef<'a, T> {
         self.tree.get_unchecked(self.index)
     }
 }
+
+impl<T> Copy for Position<T> { }
+impl<T> Clone for Position<T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Eq for Position<T> { }
+impl<T> PartialEq for Position<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (*self, *other) {
+            (Position::First, Position::First) | (Position::Last, Position::Last) => true,
+            (Position::Before(a), Position::Before(b)) | (Position::After(a), Position::After(b)) => {
+                a == b
+            },
+            _ => false,
+        }
+    }
+}
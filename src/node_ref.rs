@@ -1,11 +1,17 @@
 #![allow(expl_impl_clone_on_copy)]
 
-use super::{NodeRef, NodeId};
+use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use super::{Tree, NodeRef, NodeId, Visitor};
+use super::iter::Edge;
 
 impl<'a, T: 'a> NodeRef<'a, T> {
     /// Returns the value of this node.
     pub fn value(&self) -> &'a T {
-        &self.node.value
+        self.node.value()
     }
 
     /// Returns the ID of this node.
@@ -47,6 +53,476 @@ impl<'a, T: 'a> NodeRef<'a, T> {
     pub fn has_children(&self) -> bool {
         self.node.children.is_some()
     }
+
+    /// Returns true if this node has a parent and no siblings.
+    pub fn is_only_child(&self) -> bool {
+        self.node.parent.is_some() && !self.has_siblings()
+    }
+
+    /// Returns true if this node has a parent and no previous sibling.
+    ///
+    /// The root and other orphans are not first children.
+    pub fn is_first_child(&self) -> bool {
+        self.node.parent.is_some() && self.node.prev_sibling.is_none()
+    }
+
+    /// Returns true if this node has a parent and no next sibling.
+    ///
+    /// The root and other orphans are not last children.
+    pub fn is_last_child(&self) -> bool {
+        self.node.parent.is_some() && self.node.next_sibling.is_none()
+    }
+
+    /// Returns true if this node is a descendant of the specified node.
+    ///
+    /// A node is not a descendant of itself.
+    pub fn is_descendant_of(&self, id: NodeId<T>) -> bool {
+        self.ancestors().any(|node| node.id() == id)
+    }
+
+    /// Returns true if the specified node is an ancestor of this node.
+    ///
+    /// A node is not an ancestor of itself. This is the same as `is_descendant_of`.
+    pub fn has_ancestor(&self, id: NodeId<T>) -> bool {
+        self.is_descendant_of(id)
+    }
+
+    /// Returns true if this node is a descendant of any of the specified nodes.
+    ///
+    /// A node is not a descendant of itself. The ancestors are walked only once.
+    pub fn is_descendant_of_any(&self, ids: &[NodeId<T>]) -> bool {
+        let ids: HashSet<NodeId<T>> = ids.iter().cloned().collect();
+        self.ancestors().any(|node| ids.contains(&node.id()))
+    }
+
+    /// Returns this node's position among its parent's children and the number of those children,
+    /// or `None` if this node is an orphan.
+    pub fn grid_position(&self) -> Option<(usize, usize)> {
+        let parent = self.parent()?;
+        let mut position = 0;
+        let mut count = 0;
+        for child in parent.children() {
+            if child == *self {
+                position = count;
+            }
+            count += 1;
+        }
+        Some((position, count))
+    }
+
+    /// Returns the number of siblings before this node.
+    pub fn num_preceding_siblings(&self) -> usize {
+        self.prev_siblings().count()
+    }
+
+    /// Returns the number of siblings after this node.
+    pub fn num_following_siblings(&self) -> usize {
+        self.next_siblings().count()
+    }
+
+    /// Groups the IDs of this node's children by a key computed from their values.
+    ///
+    /// IDs within each group are in child order.
+    pub fn group_children_by<K, F>(&self, mut f: F) -> HashMap<K, Vec<NodeId<T>>>
+        where K: Eq + Hash, F: FnMut(&T) -> K {
+        let mut groups = HashMap::new();
+        for child in self.children() {
+            groups.entry(f(child.value())).or_insert_with(Vec::new).push(child.id());
+        }
+        groups
+    }
+
+    /// Returns the leftmost leaf of the subtree starting at this node, reached through first
+    /// children.
+    ///
+    /// A leaf returns itself.
+    pub fn first_leaf(&self) -> NodeRef<'a, T> {
+        self.first_children().last().unwrap_or(*self)
+    }
+
+    /// Returns the rightmost leaf of the subtree starting at this node, reached through last
+    /// children.
+    ///
+    /// A leaf returns itself.
+    pub fn last_leaf(&self) -> NodeRef<'a, T> {
+        self.last_children().last().unwrap_or(*self)
+    }
+
+    /// Returns the number of nodes in the subtree starting at this node, including itself.
+    pub fn subtree_len(&self) -> usize {
+        self.descendants().count() + 1
+    }
+
+    /// Returns the number of edges on the longest path from this node down to a leaf.
+    pub fn height(&self) -> usize {
+        let mut depth = 0;
+        let mut height = 0;
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(_) => {
+                    height = cmp::max(height, depth);
+                    depth += 1;
+                },
+                Edge::Close(_) => depth -= 1,
+            }
+        }
+        height
+    }
+
+    /// Returns true if the subtree starting at this node is height-balanced, i.e. the heights of
+    /// the children of every node in it differ by at most one.
+    ///
+    /// A node with a single child is compared as if it had a second, missing child of height -1,
+    /// as in a binary tree, so a chain of more than two nodes is not balanced.
+    pub fn is_balanced(&self) -> bool {
+        // The smallest and largest heights, and the number, of the children of each open node.
+        let mut stack: Vec<(isize, isize, usize)> = Vec::new();
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(_) => stack.push((isize::MAX, -1, 0)),
+                Edge::Close(_) => {
+                    let (min, max, count) = stack.pop().unwrap();
+                    let min = if count == 1 { -1 } else { min };
+                    if count > 0 && max - min > 1 {
+                        return false;
+                    }
+                    let height = max + 1;
+                    if let Some((parent_min, parent_max, siblings)) = stack.last_mut() {
+                        *parent_min = cmp::min(*parent_min, height);
+                        *parent_max = cmp::max(*parent_max, height);
+                        *siblings += 1;
+                    }
+                },
+            }
+        }
+        true
+    }
+
+    /// Returns the number of edges on the shortest path from this node down to a leaf.
+    ///
+    /// The subtree is searched breadth-first, stopping at the first leaf.
+    pub fn min_depth(&self) -> usize {
+        let mut queue = VecDeque::new();
+        queue.push_back((*self, 0));
+        while let Some((node, depth)) = queue.pop_front() {
+            if !node.has_children() {
+                return depth;
+            }
+            queue.extend(node.children().map(|child| (child, depth + 1)));
+        }
+        unreachable!()
+    }
+
+    /// Returns the largest number of nodes at any one depth of the subtree starting at this node.
+    ///
+    /// The subtree is searched breadth-first, one level at a time.
+    pub fn max_width(&self) -> usize {
+        let mut level = vec![*self];
+        let mut width = 1;
+        while !level.is_empty() {
+            width = cmp::max(width, level.len());
+            level = level.iter().flat_map(|node| node.children()).collect();
+        }
+        width
+    }
+
+    /// Returns the smallest and largest depths, relative to this node, of the leaves in the
+    /// subtree starting at this node.
+    ///
+    /// A leaf returns `(0, 0)`.
+    pub fn depth_range(&self) -> (usize, usize) {
+        let mut range = (usize::MAX, 0);
+        let mut depth = 0;
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    if !node.has_children() {
+                        range = (cmp::min(range.0, depth), cmp::max(range.1, depth));
+                    }
+                    depth += 1;
+                },
+                Edge::Close(_) => depth -= 1,
+            }
+        }
+        range
+    }
+
+    /// Returns the IDs of the nodes in the subtree starting at this node, grouped by depth below
+    /// this node and ordered left to right.
+    ///
+    /// The first group contains only this node.
+    pub fn ids_by_level(&self) -> Vec<Vec<NodeId<T>>> {
+        let mut levels: Vec<Vec<NodeId<T>>> = Vec::new();
+        let mut depth = 0;
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    if levels.len() == depth {
+                        levels.push(Vec::new());
+                    }
+                    levels[depth].push(node.id());
+                    depth += 1;
+                },
+                Edge::Close(_) => depth -= 1,
+            }
+        }
+        levels
+    }
+
+    /// Returns the IDs along the longest path from this node down to a leaf.
+    ///
+    /// Ties are broken in favour of earlier children. The path has `height() + 1` nodes.
+    pub fn longest_path(&self) -> Vec<NodeId<T>> {
+        let mut depth = 0;
+        let mut deepest = (0, *self);
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    if depth > deepest.0 {
+                        deepest = (depth, node);
+                    }
+                    depth += 1;
+                },
+                Edge::Close(_) => depth -= 1,
+            }
+        }
+
+        let mut path: Vec<NodeId<T>> = Some(deepest.1)
+            .into_iter()
+            .chain(deepest.1.ancestors())
+            .take(deepest.0 + 1)
+            .map(|node| node.id())
+            .collect();
+        path.reverse();
+        path
+    }
+
+    /// Returns the values of the nodes on the path from the root to this node, root first.
+    pub fn path_values(&self) -> Vec<&'a T> {
+        let mut values: Vec<&'a T> = self.ancestors_inclusive().map(|node| node.value()).collect();
+        values.reverse();
+        values
+    }
+
+    /// Returns the values of the nodes on the path from this node down to the specified
+    /// descendant, this node first.
+    ///
+    /// Returns `None` if the node is not in the subtree starting at this node.
+    pub fn path_values_to(&self, descendant: NodeId<T>) -> Option<Vec<&'a T>> {
+        let mut values = Vec::new();
+        for node in self.tree.try_validate(descendant).ok()?.ancestors_inclusive() {
+            values.push(node.value());
+            if node == *self {
+                values.reverse();
+                return Some(values);
+            }
+        }
+        None
+    }
+
+    /// Returns this node's `k`-th ancestor, where the parent is the first and this node is the
+    /// zeroth.
+    pub fn ancestor(&self, k: usize) -> Option<NodeRef<'a, T>> {
+        match k {
+            0 => Some(*self),
+            _ => self.ancestors().nth(k - 1),
+        }
+    }
+
+    /// Returns the position of the specified node in a pre-order traversal of the subtree
+    /// starting at this node, where this node is at position 0.
+    ///
+    /// Returns `None` if the node is not in the subtree.
+    pub fn preorder_offset(&self, id: NodeId<T>) -> Option<usize> {
+        if self.id() == id {
+            return Some(0);
+        }
+        self.descendants().position(|node| node.id() == id).map(|i| i + 1)
+    }
+
+    /// Returns an iterator over this node's descendants in pre-order, each paired with its rank in
+    /// a pre-order traversal of the whole tree, where the root has rank 0.
+    ///
+    /// For a node not reachable from the root, ranks are counted from its topmost ancestor.
+    pub fn descendants_with_global_rank(&self) -> impl Iterator<Item = (usize, NodeRef<'a, T>)> {
+        let mut rank = 0;
+        for node in self.ancestors_inclusive() {
+            if node.parent().is_some() {
+                let preceding: usize = node.prev_siblings().map(|sibling| sibling.subtree_len()).sum();
+                rank += 1 + preceding;
+            }
+        }
+        self.descendants().enumerate().map(move |(i, node)| (rank + 1 + i, node))
+    }
+
+    /// Returns the node following this one in pre-order.
+    pub fn next_pre_order(&self) -> Option<NodeRef<'a, T>> {
+        if let Some(first_child) = self.first_child() {
+            return Some(first_child);
+        }
+        let mut node = Some(*self);
+        while let Some(current) = node {
+            if let Some(next_sibling) = current.next_sibling() {
+                return Some(next_sibling);
+            }
+            node = current.parent();
+        }
+        None
+    }
+
+    /// Returns the node preceding this one in pre-order.
+    pub fn prev_pre_order(&self) -> Option<NodeRef<'a, T>> {
+        match self.prev_sibling() {
+            Some(prev_sibling) => prev_sibling.last_children().last().or(Some(prev_sibling)),
+            None => self.parent(),
+        }
+    }
+
+    /// Creates a new tree from the subtree starting at this node, mapping each value.
+    pub fn map_subtree<U, F>(&self, mut f: F) -> Tree<U> where F: FnMut(&T) -> U {
+        let mut tree = Tree::new(f(self.value()));
+        let mut stack = vec![0];
+        for edge in self.traverse().skip(1) {
+            match edge {
+                Edge::Open(node) => {
+                    let parent = *stack.last().unwrap();
+                    let index = tree.get_unchecked_mut(parent).append(f(node.value())).index;
+                    stack.push(index);
+                },
+                Edge::Close(_) => { let _ = stack.pop(); },
+            }
+        }
+        tree
+    }
+
+    /// Exports the subtree starting at this node as its values in pre-order and the edges between
+    /// them as pairs of parent and child positions in that order.
+    ///
+    /// This node is at position 0, and edges are listed in the pre-order of their children.
+    pub fn to_edge_list(&self) -> (Vec<&'a T>, Vec<(usize, usize)>) {
+        let mut values = Vec::new();
+        let mut edges = Vec::new();
+        let mut stack = Vec::new();
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    let position = values.len();
+                    values.push(node.value());
+                    if let Some(&parent) = stack.last() {
+                        edges.push((parent, position));
+                    }
+                    stack.push(position);
+                },
+                Edge::Close(_) => { let _ = stack.pop(); },
+            }
+        }
+        (values, edges)
+    }
+
+    /// Returns the `n`th node of the subtree starting at this node, in pre-order, where this node
+    /// is the 0th.
+    pub fn preorder_nth(&self, n: usize) -> Option<NodeRef<'a, T>> {
+        match n {
+            0 => Some(*self),
+            _ => self.descendants().nth(n - 1),
+        }
+    }
+
+    /// Returns the values of this node's descendants, not including itself, in pre-order.
+    pub fn descendant_values_vec(&self) -> Vec<&'a T> {
+        self.descendants().map(|node| node.value()).collect()
+    }
+
+    /// Returns an iterator over the values of the leaves of the subtree starting at this node, in
+    /// pre-order.
+    ///
+    /// A leaf yields only its own value.
+    pub fn leaf_values(&self) -> impl Iterator<Item = &'a T> {
+        self.traverse().filter_map(|edge| match edge {
+            Edge::Open(node) if !node.has_children() => Some(node.value()),
+            _ => None,
+        })
+    }
+
+    /// Collects the results of `f` over the values of this node's descendants, in pre-order,
+    /// keeping only those that are `Some`.
+    pub fn filter_map_values<U, F>(&self, mut f: F) -> Vec<U> where F: FnMut(&T) -> Option<U> {
+        self.descendants().filter_map(|node| f(node.value())).collect()
+    }
+
+    /// Calls a fallible function on the values of this node's descendants in pre-order, stopping
+    /// at the first error.
+    pub fn try_for_each_value<E, F>(&self, mut f: F) -> Result<(), E>
+        where F: FnMut(&T) -> Result<(), E> {
+        self.descendants().try_for_each(|node| f(node.value()))
+    }
+
+    /// Walks the subtree starting at this node, calling the visitor on entering and leaving each
+    /// node.
+    pub fn walk<V: Visitor<T>>(&self, visitor: &mut V) {
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => visitor.enter(node),
+                Edge::Close(node) => visitor.leave(node),
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a + Clone> NodeRef<'a, T> {
+    /// Clones the subtree starting at this node into a new tree.
+    pub fn to_owned(&self) -> Tree<T> {
+        self.map_subtree(T::clone)
+    }
+
+    /// Clones the subtree starting at this node into a new tree, down to `max_depth` levels below
+    /// this node.
+    ///
+    /// Nodes at `max_depth` become leaves in the clone, so a depth of 0 clones this node alone.
+    pub fn clone_subtree_to_depth(&self, max_depth: usize) -> Tree<T> {
+        let mut tree = Tree::new(self.value().clone());
+        let mut stack = vec![(*self, 0, 0)];
+        while let Some((node, index, depth)) = stack.pop() {
+            if depth == max_depth {
+                continue;
+            }
+            for child in node.children().rev() {
+                let mut parent = tree.get_unchecked_mut(index);
+                let child_index = parent.prepend(child.value().clone()).index;
+                stack.push((child, child_index, depth + 1));
+            }
+        }
+        tree
+    }
+}
+
+impl<'a, T: 'a + Hash> NodeRef<'a, T> {
+    /// Returns a hash of the values and shape of the subtree starting at this node.
+    ///
+    /// The hash does not depend on where the subtree is in the tree, so equal subtrees hash
+    /// equally.
+    pub fn structure_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    hasher.write_u8(0);
+                    node.value().hash(&mut hasher);
+                },
+                Edge::Close(_) => hasher.write_u8(1),
+            }
+        }
+        hasher.finish()
+    }
+}
+
+impl<'a, T: 'a + PartialEq> NodeRef<'a, T> {
+    /// Returns the first child of this node whose value is equal to `value`.
+    ///
+    /// Only direct children are searched.
+    pub fn find_child(&self, value: &T) -> Option<NodeRef<'a, T>> {
+        self.children().find(|child| child.value() == value)
+    }
 }
 
 impl<'a, T: 'a> Copy for NodeRef<'a, T> { }
@@ -0,0 +1,286 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use super::{Tree, NodeRef, NodeMut, NodeId, ParseError};
+use super::iter::Edge;
+
+impl<'a, T: 'a + Display> NodeRef<'a, T> {
+    /// Renders each node of the subtree starting at this node on its own line, in pre-order,
+    /// prefixed by `indent` repeated once per level below this node.
+    pub fn to_indented_lines(&self, indent: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut depth = 0;
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    lines.push(format!("{}{}", indent.repeat(depth), node.value()));
+                    depth += 1;
+                },
+                Edge::Close(_) => depth -= 1,
+            }
+        }
+        lines
+    }
+
+    /// Renders the subtree starting at this node as an S-expression.
+    ///
+    /// A node with children is written as `(value child1 child2 ...)` and a leaf as its bare
+    /// value. Values that are empty or contain whitespace, parentheses, quotes or backslashes
+    /// are written in double quotes, with quotes and backslashes escaped by a backslash.
+    pub fn to_sexpr(&self) -> String {
+        let mut sexpr = String::new();
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    if node != *self {
+                        sexpr.push(' ');
+                    }
+                    if node.has_children() {
+                        sexpr.push('(');
+                    }
+                    push_atom(&mut sexpr, &node.value().to_string());
+                },
+                Edge::Close(node) => {
+                    if node.has_children() {
+                        sexpr.push(')');
+                    }
+                },
+            }
+        }
+        sexpr
+    }
+}
+
+fn push_atom(sexpr: &mut String, atom: &str) {
+    let needs_quotes = atom.is_empty() || atom.chars().any(|c| {
+        c.is_whitespace() || c == '(' || c == ')' || c == '"' || c == '\\'
+    });
+    if !needs_quotes {
+        sexpr.push_str(atom);
+        return;
+    }
+    sexpr.push('"');
+    for c in atom.chars() {
+        if c == '"' || c == '\\' {
+            sexpr.push('\\');
+        }
+        sexpr.push(c);
+    }
+    sexpr.push('"');
+}
+
+impl Tree<String> {
+    /// Parses an S-expression into a tree.
+    ///
+    /// A group `(value child1 child2 ...)` becomes a node whose value is its first token and whose
+    /// children are the rest; a bare or quoted token becomes a leaf. This is the inverse of
+    /// `NodeRef::to_sexpr`.
+    pub fn from_sexpr(s: &str) -> Result<Tree<String>, ParseError> {
+        let mut tokens = Tokens { s, chars: s.char_indices().peekable() };
+        let tree = match tokens.next()? {
+            None => return Err(ParseError::ExpectedValue { offset: s.len() }),
+            Some(Token::Atom(value, _)) => Tree::new(value),
+            Some(Token::Close(offset)) => return Err(ParseError::UnopenedParen { offset }),
+            Some(Token::Open(offset)) => {
+                let mut tree = Tree::new(tokens.group_value(offset)?);
+                let root = tree.root().id();
+                let mut stack: Vec<(NodeId<String>, usize)> = vec![(root, offset)];
+                while let Some(&(parent, open)) = stack.last() {
+                    match tokens.next()? {
+                        None => return Err(ParseError::UnclosedParen { offset: open }),
+                        Some(Token::Close(_)) => { let _ = stack.pop(); },
+                        Some(Token::Atom(value, _)) => {
+                            let _ = tree.get_mut(parent).append(value);
+                        },
+                        Some(Token::Open(offset)) => {
+                            let value = tokens.group_value(offset)?;
+                            let id = tree.get_mut(parent).append(value).id();
+                            stack.push((id, offset));
+                        },
+                    }
+                }
+                tree
+            },
+        };
+        match tokens.next()? {
+            None => Ok(tree),
+            Some(Token::Close(offset)) => Err(ParseError::UnopenedParen { offset }),
+            Some(Token::Open(offset)) | Some(Token::Atom(_, offset)) => {
+                Err(ParseError::TrailingInput { offset })
+            },
+        }
+    }
+}
+
+impl<'a, T: 'a + From<String>> NodeMut<'a, T> {
+    /// Parses an S-expression with `Tree::from_sexpr` and appends the resulting subtree to this
+    /// node's children, returning the ID of the subtree's root.
+    ///
+    /// Nothing is appended if parsing fails.
+    pub fn append_sexpr(&mut self, s: &str) -> Result<NodeId<T>, ParseError> {
+        let subtree = Tree::from_sexpr(s)?.map(T::from);
+        Ok(self.append_subtree(subtree).id())
+    }
+}
+
+impl Tree<String> {
+    /// Parses an indented outline into a tree, one node per line.
+    ///
+    /// Each line's depth is its number of leading spaces divided by `indent_unit`, and its value
+    /// is the rest of the line without trailing whitespace. Blank lines are skipped. This is the
+    /// inverse of `NodeRef::to_indented_lines` with an indent of `indent_unit` spaces.
+    ///
+    /// Only spaces count as indentation; a tab among a line's leading whitespace is an error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indent_unit` is zero.
+    pub fn from_indented(s: &str, indent_unit: usize) -> Result<Tree<String>, ParseError> {
+        assert!(indent_unit > 0, "indent unit must be positive");
+        let mut tree: Option<Tree<String>> = None;
+        let mut stack: Vec<NodeId<String>> = Vec::new();
+        let mut offset = 0;
+
+        for line in s.split('\n') {
+            let start = offset;
+            offset += line.len() + 1;
+            let value = line.trim_start_matches(' ').trim_end();
+            if value.is_empty() {
+                continue;
+            }
+            let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+            if let Some(tab) = line[..indent].find('\t') {
+                return Err(ParseError::TabIndent { offset: start + tab });
+            }
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            if spaces % indent_unit != 0 {
+                return Err(ParseError::BadIndent { offset: start });
+            }
+            let depth = spaces / indent_unit;
+
+            match tree {
+                None if depth == 0 => {
+                    let root = Tree::new(value.to_owned());
+                    stack.push(root.root().id());
+                    tree = Some(root);
+                },
+                None => return Err(ParseError::IndentJump { offset: start }),
+                Some(_) if depth == 0 => {
+                    return Err(ParseError::TrailingInput { offset: start + spaces });
+                },
+                Some(_) if depth > stack.len() => {
+                    return Err(ParseError::IndentJump { offset: start });
+                },
+                Some(ref mut tree) => {
+                    stack.truncate(depth);
+                    let parent = stack[depth - 1];
+                    let id = tree.get_mut(parent).append(value.to_owned()).id();
+                    stack.push(id);
+                },
+            }
+        }
+
+        tree.ok_or(ParseError::ExpectedValue { offset: s.len() })
+    }
+}
+
+enum Token {
+    Open(usize),
+    Close(usize),
+    Atom(String, usize),
+}
+
+struct Tokens<'s> {
+    s: &'s str,
+    chars: Peekable<CharIndices<'s>>,
+}
+
+impl<'s> Tokens<'s> {
+    fn next(&mut self) -> Result<Option<Token>, ParseError> {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() { break; }
+            let _ = self.chars.next();
+        }
+        let (start, c) = match self.chars.next() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        match c {
+            '(' => Ok(Some(Token::Open(start))),
+            ')' => Ok(Some(Token::Close(start))),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match self.chars.next() {
+                        None => return Err(ParseError::UnclosedQuote { offset: start }),
+                        Some((_, '"')) => return Ok(Some(Token::Atom(value, start))),
+                        Some((_, '\\')) => match self.chars.next() {
+                            None => return Err(ParseError::UnclosedQuote { offset: start }),
+                            Some((_, c)) => value.push(c),
+                        },
+                        Some((_, c)) => value.push(c),
+                    }
+                }
+            },
+            _ => {
+                let mut end = self.s.len();
+                while let Some(&(offset, c)) = self.chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        end = offset;
+                        break;
+                    }
+                    let _ = self.chars.next();
+                }
+                Ok(Some(Token::Atom(self.s[start..end].to_owned(), start)))
+            },
+        }
+    }
+
+    // Reads the value at the start of the group opened at `open`.
+    fn group_value(&mut self, open: usize) -> Result<String, ParseError> {
+        match self.next()? {
+            Some(Token::Atom(value, _)) => Ok(value),
+            Some(Token::Close(_)) => Err(ParseError::EmptyParens { offset: open }),
+            Some(Token::Open(offset)) => Err(ParseError::ExpectedValue { offset }),
+            None => Err(ParseError::UnclosedParen { offset: open }),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ParseError::ExpectedValue { offset } => {
+                write!(f, "expected value at byte {}", offset)
+            },
+            ParseError::EmptyParens { offset } => {
+                write!(f, "empty parentheses at byte {}", offset)
+            },
+            ParseError::UnclosedParen { offset } => {
+                write!(f, "unclosed parenthesis at byte {}", offset)
+            },
+            ParseError::UnopenedParen { offset } => {
+                write!(f, "unopened parenthesis at byte {}", offset)
+            },
+            ParseError::UnclosedQuote { offset } => {
+                write!(f, "unclosed quote at byte {}", offset)
+            },
+            ParseError::TrailingInput { offset } => {
+                write!(f, "trailing input at byte {}", offset)
+            },
+            ParseError::BadIndent { offset } => {
+                write!(f, "bad indentation at byte {}", offset)
+            },
+            ParseError::IndentJump { offset } => {
+                write!(f, "indentation skips a level at byte {}", offset)
+            },
+            ParseError::TabIndent { offset } => {
+                write!(f, "tab in indentation at byte {}", offset)
+            },
+        }
+    }
+}
+
+impl Error for ParseError { }
@@ -59,6 +59,33 @@ fn ancestors() {
     );
 }
 
+#[test]
+fn ancestors_until() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' => { 'e' } } }, 'x' });
+    let b = tree.root().first_child().unwrap();
+    let e = b.first_child().unwrap().first_child().unwrap().first_child().unwrap();
+    let values = |stop| e.ancestors_until(stop).map(|n| *n.value()).collect::<String>();
+    assert_eq!("dc", values(b.id()));
+    assert_eq!("dcb", values(tree.root().id()));
+    assert_eq!("dcba", values(tree.root().last_child().unwrap().id()));
+    assert_eq!("dcba", values(e.id()));
+}
+
+#[test]
+fn ancestors_inclusive() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } } });
+    let d = tree.root()
+        .last_child().unwrap()
+        .last_child().unwrap()
+        .last_child().unwrap();
+    assert_eq!(
+        vec![&'d', &'c', &'b', &'a'],
+        d.ancestors_inclusive().map(|n| n.value()).collect::<Vec<_>>()
+    );
+    assert_eq!(Some(d), d.ancestors_inclusive().next());
+    assert_eq!(Some(tree.root()), d.ancestors_inclusive().last());
+}
+
 #[test]
 fn prev_siblings() {
     let tree = tree!('a' => { 'b', 'c', 'd' });
@@ -105,6 +132,20 @@ fn children_rev() {
     );
 }
 
+#[test]
+fn child_windows() {
+    let tree = tree!('a' => { 'b', 'c' => { 'x' }, 'd', 'e' });
+    assert_eq!(
+        vec![('b', 'c'), ('c', 'd'), ('d', 'e')],
+        tree.root()
+            .child_windows()
+            .map(|(l, r)| (*l.value(), *r.value()))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(0, tree.root().children().nth(1).unwrap().child_windows().count());
+    assert_eq!(0, tree.root().first_child().unwrap().child_windows().count());
+}
+
 #[test]
 fn first_children() {
     let tree = tree!('a' => { 'b' => { 'd', 'e' }, 'c' });
@@ -174,3 +215,149 @@ fn traverse() {
         &traversal[..]
     );
 }
+
+#[test]
+fn node_ids() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    tree.orphan('e');
+
+    assert_eq!(tree.len(), tree.node_ids().count());
+    assert_eq!(
+        vec![&'a', &'b', &'c', &'d', &'e'],
+        tree.node_ids().map(|id| tree.get(id).value()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn descendants() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' }, 'f' });
+    assert_eq!(
+        vec![&'b', &'c', &'d', &'e', &'f'],
+        tree.root().descendants().map(|n| n.value()).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![&'c', &'d', &'e'],
+        tree.root().first_child().unwrap().descendants().map(|n| n.value()).collect::<Vec<_>>()
+    );
+    assert_eq!(0, tree.root().last_child().unwrap().descendants().count());
+}
+
+#[test]
+fn descendants_rev() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' => { 'f', 'g' } }, 'h', 'i' => { 'j' } });
+    let b = tree.root().first_child().unwrap();
+    for node in [tree.root(), b, tree.root().last_child().unwrap()] {
+        let mut forward = node.descendants().collect::<Vec<_>>();
+        forward.reverse();
+        assert_eq!(forward, node.descendants().rev().collect::<Vec<_>>());
+    }
+    assert_eq!(
+        vec![&'g', &'f', &'e', &'d', &'c'],
+        b.descendants().rev().map(|n| n.value()).collect::<Vec<_>>()
+    );
+    assert_eq!(0, tree.root().children().nth(1).unwrap().descendants().rev().count());
+
+    let mut both = tree.root().descendants();
+    assert_eq!(Some(&'b'), both.next().map(|n| n.value()));
+    assert_eq!(Some(&'j'), both.next_back().map(|n| n.value()));
+    assert_eq!(7, both.count());
+}
+
+#[test]
+fn chunk_by_depth() {
+    let tree = tree!('a' => { 'b', 'c' => { 'd', 'e' => { 'f' }, 'g' }, 'h', 'i' });
+    let chunks = |node: ego_tree::NodeRef<char>| {
+        node.descendants()
+            .chunk_by_depth()
+            .map(|chunk| chunk.into_iter().map(|n| *n.value()).collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["bc", "de", "f", "g", "hi"], chunks(tree.root()));
+    assert_eq!(vec!["de", "f", "g"], chunks(tree.root().children().nth(1).unwrap()));
+    assert!(chunks(tree.root().first_child().unwrap()).is_empty());
+
+    let mut descendants = tree.root().descendants();
+    let _ = descendants.nth(2);
+    assert_eq!(
+        vec![vec![&'e'], vec![&'f'], vec![&'g'], vec![&'h', &'i']],
+        descendants
+            .chunk_by_depth()
+            .map(|chunk| chunk.into_iter().map(|n| n.value()).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn depth_transitions() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' => { 'e' } } }, 'f' => { 'g' }, 'h' });
+    assert_eq!(
+        vec![('b', 1), ('c', 1), ('d', 1), ('e', 1), ('f', -3), ('g', 1), ('h', -1)],
+        tree.root()
+            .descendants()
+            .depth_transitions()
+            .map(|(n, change)| (*n.value(), change))
+            .collect::<Vec<_>>()
+    );
+
+    let mut descendants = tree.root().descendants();
+    let _ = descendants.nth(3);
+    assert_eq!(
+        vec![('f', 1), ('g', 1), ('h', -1)],
+        descendants.depth_transitions().map(|(n, change)| (*n.value(), change)).collect::<Vec<_>>()
+    );
+    assert_eq!(0, tree.root().last_child().unwrap().descendants().depth_transitions().count());
+}
+
+#[test]
+fn descendants_with_paths() {
+    let tree = tree!('a' => { 'x', 'b' => { 'c' => { 'd' }, 'e', 'f' => { 'g', 'h' } } });
+    let b = tree.root().last_child().unwrap();
+    assert_eq!(
+        vec![
+            (vec![0], 'c'),
+            (vec![0, 0], 'd'),
+            (vec![1], 'e'),
+            (vec![2], 'f'),
+            (vec![2, 0], 'g'),
+            (vec![2, 1], 'h'),
+        ],
+        b.descendants_with_paths().map(|(path, n)| (path, *n.value())).collect::<Vec<_>>()
+    );
+    assert_eq!(0, b.first_child().unwrap().first_child().unwrap().descendants_with_paths().count());
+}
+
+#[test]
+fn descendants_within() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' }, 'f' => { 'g' } });
+    let values = |depth| {
+        tree.root().descendants_within(depth).map(|n| *n.value()).collect::<String>()
+    };
+    assert_eq!("", values(0));
+    assert_eq!("bf", values(1));
+    assert_eq!("bcefg", values(2));
+    assert_eq!("bcdefg", values(3));
+    assert_eq!("bcdefg", values(10));
+}
+
+#[test]
+fn descendant_ids_bfs() {
+    let tree = tree!('a' => { 'b' => { 'd' => { 'g' }, 'e' }, 'c' => { 'f' => { 'h', 'i' } } });
+    assert_eq!(
+        vec![&'b', &'c', &'d', &'e', &'f', &'g', &'h', &'i'],
+        tree.root().descendant_ids_bfs().map(|id| tree.get(id).value()).collect::<Vec<_>>()
+    );
+    let c = tree.root().last_child().unwrap();
+    assert_eq!(
+        vec![&'f', &'h', &'i'],
+        c.descendant_ids_bfs().map(|id| tree.get(id).value()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn edges() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let edges = tree.edges()
+        .map(|(parent, child)| (*tree.get(parent).value(), *tree.get(child).value()))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![('a', 'b'), ('b', 'c'), ('a', 'd')], edges);
+}
@@ -1,3 +1,4 @@
+#[macro_use]
 extern crate ego_tree;
 
 use ego_tree::Tree;
@@ -50,3 +51,13 @@ fn cloned_tree() {
     let two = one.clone();
     one.get(two.root().id());
 }
+
+#[test]
+fn tree_id() {
+    let tree = tree!('a' => { 'b' });
+    let other = tree!('a' => { 'b' });
+    let root = tree.root().id();
+    let b = tree.root().first_child().unwrap().id();
+    assert_eq!(root.tree_id(), b.tree_id());
+    assert!(root.tree_id() != other.root().id().tree_id());
+}
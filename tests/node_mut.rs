@@ -1,7 +1,8 @@
 #[macro_use]
 extern crate ego_tree;
 
-use ego_tree::NodeRef;
+use ego_tree::{Tree, NodeRef, Position, ParseError};
+use ego_tree::iter::Edge;
 
 #[test]
 fn value() {
@@ -206,6 +207,126 @@ fn prepend_3() {
     assert_eq!(None, d.next_sibling());
 }
 
+#[test]
+fn append_with() {
+    let mut tree = tree!('a');
+    let b_id = tree.root_mut().append_with('b', |b| {
+        b.append('c');
+        b.append_with('d', |d| {
+            d.append('e');
+        });
+    });
+    tree.root_mut().append('f');
+
+    assert_eq!(tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' }), tree);
+    assert_eq!(tree.root().first_child(), Some(tree.get(b_id)));
+}
+
+#[test]
+fn append_subtree() {
+    let mut tree = tree!('a' => { 'b' });
+    let other = tree!('c' => { 'd' => { 'e' }, 'f' });
+    let c_id = tree.root_mut().append_subtree(other).id();
+
+    assert_eq!(tree!('a' => { 'b', 'c' => { 'd' => { 'e' }, 'f' } }), tree);
+    assert_eq!(Some(tree.get(c_id)), tree.root().last_child());
+}
+
+#[test]
+fn append_subtree_reserves() {
+    let mut other = Tree::new(0);
+    for i in 1..1000 {
+        other.root_mut().append(i);
+    }
+    let mut tree = Tree::new(0);
+    tree.root_mut().append_subtree(other);
+
+    assert!(tree.capacity() >= 1001);
+    assert!(tree.capacity() < 1024);
+}
+
+#[test]
+fn append_moved() {
+    let mut tree = tree!('a' => { 'b' });
+    let mut src = tree!('x' => { 'c' => { 'd' => { 'e' }, 'f' }, 'y' });
+    let c = src.root().first_child().unwrap().id();
+    let d = src.get(c).first_child().unwrap().id();
+
+    let moved = tree.root_mut().append_moved(&mut src, c).id();
+    assert_eq!("(a b (c (d e) f))", tree.root().to_sexpr());
+    assert_eq!(Some(tree.get(moved)), tree.root().last_child());
+    assert_eq!("(x y)", src.root().to_sexpr());
+    assert_eq!(2, src.len());
+    assert_eq!(None, src.index_of(d));
+    assert_eq!(Ok(()), tree.sanity_check());
+    assert_eq!(Ok(()), src.sanity_check());
+}
+
+#[test]
+#[should_panic(expected = "cannot remove the root")]
+fn append_moved_root() {
+    let mut tree = tree!('a');
+    let mut src = tree!('x' => { 'y' });
+    let root = src.root().id();
+    let _ = tree.root_mut().append_moved(&mut src, root);
+}
+
+#[test]
+fn insert_children_at() {
+    let children = |tree: &Tree<char>| {
+        tree.root().children().map(|n| *n.value()).collect::<String>()
+    };
+
+    let mut tree = tree!('a' => { 'x', 'y' });
+    tree.root_mut().insert_children_at(0, vec!['b', 'c']);
+    assert_eq!("bcxy", children(&tree));
+
+    tree.root_mut().insert_children_at(3, vec!['d', 'e']);
+    assert_eq!("bcxdey", children(&tree));
+
+    tree.root_mut().insert_children_at(6, vec!['f']);
+    assert_eq!("bcxdeyf", children(&tree));
+    assert_eq!(
+        "fyedxcb",
+        tree.root().children().rev().map(|n| *n.value()).collect::<String>()
+    );
+}
+
+#[test]
+#[should_panic]
+fn insert_children_at_out_of_bounds() {
+    let mut tree = tree!('a' => { 'b' });
+    tree.root_mut().insert_children_at(2, vec!['c']);
+}
+
+#[test]
+fn splice_children() {
+    let children = |tree: &Tree<char>| {
+        tree.root().children().map(|n| *n.value()).collect::<String>()
+    };
+
+    let mut tree = tree!('a' => { 'x', 'y' });
+    tree.root_mut().splice_children(0, &['b', 'c']);
+    assert_eq!("bcxy", children(&tree));
+
+    tree.root_mut().splice_children(3, &['d', 'e']);
+    assert_eq!("bcxdey", children(&tree));
+
+    tree.root_mut().splice_children(6, &['f']);
+    assert_eq!("bcxdeyf", children(&tree));
+
+    tree.root_mut().splice_children(2, &[]);
+    assert_eq!(7, tree.root().children().count());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+#[should_panic(expected = "child position out of bounds")]
+fn splice_children_out_of_bounds() {
+    let mut tree = tree!('a' => { 'b' });
+    tree.root_mut().splice_children(2, &['c']);
+}
+
 #[test]
 fn insert_before_first() {
     let mut tree = tree!('a' => { 'c' });
@@ -345,9 +466,413 @@ fn reparent_from_id_prepend() {
     assert_eq!(Some(d), f.prev_sibling());
 }
 
+#[test]
+fn drain_subtree() {
+    let mut tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' }, 'f' });
+    let b_id = tree.root().first_child().unwrap().id();
+    let values = tree.get_mut(b_id).drain_subtree();
+
+    assert_eq!(vec!['b', 'c', 'd', 'e'], values);
+    assert_eq!(2, tree.len());
+    assert_eq!(vec![&'a', &'f'], tree.values().collect::<Vec<_>>());
+    assert!(tree.root().first_child().unwrap().is_only_child());
+}
+
+#[test]
+#[should_panic]
+fn drain_subtree_root() {
+    let mut tree = tree!('a' => { 'b' });
+    tree.root_mut().drain_subtree();
+}
+
+#[test]
+fn sort_children_by() {
+    let mut tree = tree!('a' => { 'd', 'b' => { 'z', 'y' }, 'c' });
+    tree.root_mut().sort_children_by(|a, b| a.cmp(b));
+
+    let root = tree.root();
+    assert_eq!(
+        vec![&'b', &'c', &'d'],
+        root.children().map(|n| n.value()).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![&'d', &'c', &'b'],
+        root.children().rev().map(|n| n.value()).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![&'z', &'y'],
+        root.first_child().unwrap().children().map(|n| n.value()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn sort_children_by_cached_key() {
+    let mut tree = tree!("root" => { "ccc", "a", "bb", "dddd", "e" });
+    let mut calls = 0;
+    tree.root_mut().sort_children_by_cached_key(|s| {
+        calls += 1;
+        s.len()
+    });
+
+    assert_eq!(5, calls);
+    assert_eq!(
+        vec![&"a", &"e", &"bb", &"ccc", &"dddd"],
+        tree.root().children().map(|n| n.value()).collect::<Vec<_>>()
+    );
+    assert_eq!(Some(&"dddd"), tree.root().last_child().map(|n| n.value()));
+}
+
+#[test]
+fn sort_descendants_by() {
+    let mut tree = tree!(0 => { 6 => { 9, 7, 8 }, 1 => { 3 => { 5, 4 }, 2 } });
+    tree.root_mut().sort_descendants_by(|a, b| a.cmp(b));
+
+    let values = tree.root().traverse().filter_map(|e| match e {
+        Edge::Open(node) => Some(*node.value()),
+        Edge::Close(_) => None,
+    }).collect::<Vec<_>>();
+    assert_eq!((0..10).collect::<Vec<_>>(), values);
+
+    for node in tree.nodes() {
+        for child in node.children() {
+            assert_eq!(Some(node), child.parent());
+            if let Some(next) = child.next_sibling() {
+                assert_eq!(Some(child), next.prev_sibling());
+            }
+        }
+        assert_eq!(
+            node.children().count(),
+            node.children().rev().count()
+        );
+    }
+}
+
+#[test]
+fn partition_children_by() {
+    let mut tree = tree!(0 => { 1, 2 => { 7 }, 3, 4 });
+    tree.root_mut().partition_children_by(|n| n % 2 == 0, 10, 11);
+
+    let root = tree.root();
+    let even = root.first_child().unwrap();
+    let odd = root.last_child().unwrap();
+    let values = |node: NodeRef<i32>| node.children().map(|n| *n.value()).collect::<Vec<_>>();
+
+    assert_eq!(vec![10, 11], values(root));
+    assert_eq!(vec![2, 4], values(even));
+    assert_eq!(vec![1, 3], values(odd));
+    assert_eq!(vec![7], values(even.first_child().unwrap()));
+    assert_eq!(Some(even), even.last_child().unwrap().parent());
+    assert_eq!(None, odd.first_child().unwrap().prev_sibling());
+}
+
+#[test]
+fn retain_children() {
+    let mut tree = tree!(0 => { 1, 2 => { 7 }, 3, 4, 5 });
+    tree.root_mut().retain_children(|n| n % 2 == 0);
+
+    let root = tree.root();
+    let two = root.first_child().unwrap();
+    let four = root.last_child().unwrap();
+    assert_eq!(vec![&2, &4], root.children().map(|n| n.value()).collect::<Vec<_>>());
+    assert_eq!(Some(four), two.next_sibling());
+    assert_eq!(Some(two), four.prev_sibling());
+    assert_eq!(None, two.prev_sibling());
+    assert_eq!(None, four.next_sibling());
+    assert_eq!(Some(root), four.parent());
+    assert_eq!(&7, two.first_child().unwrap().value());
+    assert_eq!(
+        vec![&1, &3, &5],
+        tree.nodes().skip(1).filter(|n| n.parent().is_none()).map(|n| n.value()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn append_slice() {
+    let mut tree = tree!('a' => { 'b' });
+    tree.root_mut().append_slice(&['c', 'd', 'e', 'f', 'g']);
+
+    assert_eq!(
+        vec![&'b', &'c', &'d', &'e', &'f', &'g'],
+        tree.root().children().map(|n| n.value()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn duplicate_subtree() {
+    let mut tree = tree!('a' => { 'b', 'c' => { 'd' => { 'e' }, 'f' }, 'g' });
+    let c_id = tree.root().first_child().unwrap().next_sibling().unwrap().id();
+    let copy_id = tree.get_mut(c_id).duplicate_subtree();
+
+    let c = tree.get(c_id);
+    let copy = tree.get(copy_id);
+    assert_eq!(Some(copy), c.next_sibling());
+    assert_eq!(&'g', copy.next_sibling().unwrap().value());
+    assert_eq!(Some(tree.root()), copy.parent());
+    assert_eq!(
+        vec![&'c', &'d', &'e', &'f'],
+        copy.traverse().filter_map(|e| match e {
+            Edge::Open(node) => Some(node.value()),
+            Edge::Close(_) => None,
+        }).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+#[should_panic]
+fn duplicate_subtree_root() {
+    let mut tree = tree!('a');
+    tree.root_mut().duplicate_subtree();
+}
+
 #[test]
 fn into() {
     let mut tree = tree!('a');
     let node_ref: NodeRef<_> = tree.root_mut().into();
     assert_eq!(&'a', node_ref.value());
 }
+
+#[test]
+fn reattach_to() {
+    let reattach = |position: fn(&Tree<char>) -> Position<char>| {
+        let mut tree = tree!('a' => { 'b' => { 'x' => { 'y' } }, 'c', 'd' });
+        let x = tree.root().first_child().unwrap().first_child().unwrap().id();
+        let root = tree.root().id();
+        let position = position(&tree);
+        tree.get_mut(x).reattach_to(root, position);
+        assert_eq!(Ok(()), tree.sanity_check());
+        tree.root().to_sexpr()
+    };
+
+    assert_eq!("(a (x y) b c d)", reattach(|_| Position::First));
+    assert_eq!("(a b c d (x y))", reattach(|_| Position::Last));
+    assert_eq!(
+        "(a b (x y) c d)",
+        reattach(|tree| Position::Before(tree.root().children().nth(1).unwrap().id()))
+    );
+    assert_eq!(
+        "(a b c (x y) d)",
+        reattach(|tree| Position::After(tree.root().children().nth(1).unwrap().id()))
+    );
+    assert_eq!(
+        "(a b c d (x y))",
+        reattach(|tree| Position::After(tree.root().last_child().unwrap().id()))
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot reattach a node below itself")]
+fn reattach_to_cycle() {
+    let mut tree = tree!('a' => { 'b' => { 'c' => { 'd' } } });
+    let b = tree.root().first_child().unwrap().id();
+    let d = tree.get(b).first_child().unwrap().first_child().unwrap().id();
+    tree.get_mut(b).reattach_to(d, Position::Last);
+}
+
+#[test]
+#[should_panic(expected = "cannot reattach the root")]
+fn reattach_to_root() {
+    let mut tree = tree!('a' => { 'b' });
+    let x = tree.orphan('x').id();
+    tree.root_mut().reattach_to(x, Position::Last);
+}
+
+#[test]
+#[should_panic(expected = "sibling is not a child of the new parent")]
+fn reattach_to_foreign_sibling() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let b = tree.root().first_child().unwrap().id();
+    let c = tree.get(b).first_child().unwrap().id();
+    let d = tree.root().last_child().unwrap().id();
+    let root = tree.root().id();
+    tree.get_mut(d).reattach_to(root, Position::Before(c));
+}
+
+#[test]
+fn splice_out() {
+    let mut tree = tree!('a' => { 'b', 'c' => { 'd', 'e' => { 'f' }, 'g' }, 'h' });
+    let c = tree.root().children().nth(1).unwrap().id();
+    tree.get_mut(c).splice_out();
+
+    assert_eq!("(a b d (e f) g h)", tree.root().to_sexpr());
+    assert_eq!("c", tree.get(c).to_sexpr());
+    assert_eq!(None, tree.get(c).parent());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn splice_out_leaf() {
+    let mut tree = tree!('a' => { 'b', 'c', 'd' });
+    let c = tree.root().children().nth(1).unwrap().id();
+    tree.get_mut(c).splice_out();
+
+    assert_eq!("(a b d)", tree.root().to_sexpr());
+    assert_eq!(None, tree.get(c).parent());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn splice_out_only_child() {
+    let mut tree = tree!('a' => { 'b' => { 'c', 'd' } });
+    let b = tree.root().first_child().unwrap().id();
+    tree.get_mut(b).splice_out();
+
+    assert_eq!("(a c d)", tree.root().to_sexpr());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn append_sexpr() {
+    let mut tree = Tree::new(String::from("root"));
+    let _ = tree.root_mut().append(String::from("first"));
+    let id = tree.root_mut().append_sexpr("(a (b c) d)").unwrap();
+
+    assert_eq!("(root first (a (b c) d))", tree.root().to_sexpr());
+    assert_eq!(Some(tree.root()), tree.get(id).parent());
+    assert_eq!(
+        vec!["a", "b", "c", "d"],
+        tree.get(id).traverse().filter_map(|edge| match edge {
+            Edge::Open(node) => Some(node.value().as_str()),
+            Edge::Close(_) => None,
+        }).collect::<Vec<_>>()
+    );
+
+    assert_eq!(Err(ParseError::EmptyParens { offset: 3 }), tree.root_mut().append_sexpr("(a ())"));
+    assert_eq!(6, tree.len());
+}
+
+#[test]
+fn fill_subtree() {
+    let mut tree = tree!(1 => { 2 => { 3, 4 => { 5 } }, 6 });
+    let two = tree.root().first_child().unwrap().id();
+    tree.get_mut(two).fill_subtree(0);
+
+    assert_eq!(tree!(1 => { 0 => { 0, 0 => { 0 } }, 6 }), tree);
+    assert!(tree.get(two).descendants().all(|node| *node.value() == 0));
+}
+
+#[test]
+fn reconcile_children_by_value() {
+    let mut tree = tree!('a' => { 'b' => { 'x' }, 'c', 'd' });
+    let b = tree.root().first_child().unwrap().id();
+    let d = tree.root().last_child().unwrap().id();
+
+    tree.root_mut().reconcile_children_by_value(&['d', 'c', 'b']);
+    assert_eq!("(a d c (b x))", tree.root().to_sexpr());
+    assert_eq!(Some(tree.get(d)), tree.root().first_child());
+
+    tree.root_mut().reconcile_children_by_value(&['e', 'b', 'c', 'd', 'c']);
+    assert_eq!("(a e (b x) c d c)", tree.root().to_sexpr());
+    assert_eq!(Some(b), tree.root().children().nth(1).map(|n| n.id()));
+
+    tree.root_mut().reconcile_children_by_value(&['c', 'b']);
+    assert_eq!("(a c (b x))", tree.root().to_sexpr());
+    assert_eq!(None, tree.get(d).parent());
+    assert_eq!(Ok(()), tree.sanity_check());
+
+    tree.root_mut().reconcile_children_by_value(&[]);
+    assert!(!tree.root().has_children());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn collapse_single_child_chains() {
+    let s = String::from;
+    let concat = |mut a: String, b: String| { a.push_str(&b); a };
+
+    let mut tree = tree!(s("a") => { s("b") => { s("c") => { s("d") } } });
+    tree.root_mut().collapse_single_child_chains(concat);
+    assert_eq!("abcd", tree.root().to_sexpr());
+    assert_eq!(1, tree.len());
+
+    let mut tree = tree!(s("a") => {
+        s("b") => { s("c") => { s("d"), s("e") => { s("f") } } },
+        s("g"),
+    });
+    tree.root_mut().collapse_single_child_chains(concat);
+    assert_eq!("(a (bc d ef) g)", tree.root().to_sexpr());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn reorder_children() {
+    let mut tree = tree!('a' => { 'b', 'c' => { 'd' }, 'e' });
+    let ids = tree.root().children().map(|n| n.id()).collect::<Vec<_>>();
+    tree.root_mut().reorder_children(&[ids[2], ids[0], ids[1]]);
+
+    assert_eq!("(a e b (c d))", tree.root().to_sexpr());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+#[should_panic(expected = "order is not a permutation of the children")]
+fn reorder_children_missing() {
+    let mut tree = tree!('a' => { 'b', 'c', 'e' });
+    let ids = tree.root().children().map(|n| n.id()).collect::<Vec<_>>();
+    tree.root_mut().reorder_children(&[ids[2], ids[0]]);
+}
+
+#[test]
+#[should_panic(expected = "order is not a permutation of the children")]
+fn reorder_children_extra() {
+    let mut tree = tree!('a' => { 'b' => { 'd' }, 'c' });
+    let b = tree.root().first_child().unwrap();
+    let order = vec![b.id(), b.first_child().unwrap().id(), tree.root().last_child().unwrap().id()];
+    tree.root_mut().reorder_children(&order);
+}
+
+#[test]
+#[should_panic(expected = "order is not a permutation of the children")]
+fn reorder_children_foreign() {
+    let mut tree = tree!('a' => { 'b' });
+    let other = tree!('a' => { 'b' });
+    let order = vec![other.root().first_child().unwrap().id()];
+    tree.root_mut().reorder_children(&order);
+}
+
+#[test]
+fn isolate() {
+    let mut tree = tree!('a' => { 'b', 'c' => { 'd', 'e' => { 'f' } }, 'g' });
+    let c = tree.root().children().nth(1).unwrap().id();
+    let expected = tree.get(c).children().map(|n| n.id()).collect::<Vec<_>>();
+
+    let (value, children) = tree.get_mut(c).isolate();
+    assert_eq!('c', value);
+    assert_eq!(expected, children);
+    assert_eq!("(a b d (e f) g)", tree.root().to_sexpr());
+    assert_eq!(Some(tree.root()), tree.get(children[0]).parent());
+    assert_eq!(6, tree.len());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+#[should_panic(expected = "cannot remove the root")]
+fn isolate_root() {
+    let mut tree = tree!('a' => { 'b' });
+    let _ = tree.root_mut().isolate();
+}
+
+#[test]
+fn swap_remove_child() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd', 'e' => { 'f' } });
+    let b = tree.root().first_child().unwrap().id();
+    let e = tree.root().last_child().unwrap().id();
+
+    assert_eq!('b', tree.root_mut().swap_remove_child(b));
+    assert_eq!("(a (e f) d)", tree.root().to_sexpr());
+    assert_eq!(Some(e), tree.root().first_child().map(|n| n.id()));
+    assert_eq!(4, tree.len());
+    assert_eq!(Ok(()), tree.sanity_check());
+
+    let d = tree.root().last_child().unwrap().id();
+    assert_eq!('d', tree.root_mut().swap_remove_child(d));
+    assert_eq!("(a (e f))", tree.root().to_sexpr());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+#[should_panic(expected = "node is not a child of this node")]
+fn swap_remove_child_not_child() {
+    let mut tree = tree!('a' => { 'b' => { 'c' } });
+    let c = tree.root().first_child().unwrap().first_child().unwrap().id();
+    let _ = tree.root_mut().swap_remove_child(c);
+}
@@ -1,6 +1,9 @@
 #[macro_use]
 extern crate ego_tree;
 
+use ego_tree::{NodeRef, Visitor};
+use ego_tree::iter::Edge;
+
 #[test]
 fn value() {
     let tree = tree!('a');
@@ -60,6 +63,541 @@ fn has_children() {
     assert_eq!(false, tree.root().first_child().unwrap().has_children());
 }
 
+#[test]
+fn is_only_child() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let b = tree.root().first_child().unwrap();
+    assert!(!tree.root().is_only_child());
+    assert!(!b.is_only_child());
+    assert!(b.first_child().unwrap().is_only_child());
+}
+
+#[test]
+fn grid_position() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd', 'e' });
+    let positions = tree.root().children().map(|n| n.grid_position()).collect::<Vec<_>>();
+    assert_eq!(vec![Some((0, 3)), Some((1, 3)), Some((2, 3))], positions);
+    assert_eq!(Some((0, 1)), tree.root().first_child().unwrap().first_child().unwrap().grid_position());
+    assert_eq!(None, tree.root().grid_position());
+}
+
+#[test]
+fn is_first_child() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd', 'e' });
+    let b = tree.root().first_child().unwrap();
+    assert!(!tree.root().is_first_child());
+    assert!(b.is_first_child());
+    assert!(!b.next_sibling().unwrap().is_first_child());
+    assert!(!tree.root().last_child().unwrap().is_first_child());
+    assert!(b.first_child().unwrap().is_first_child());
+}
+
+#[test]
+fn is_last_child() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd', 'e' });
+    let b = tree.root().first_child().unwrap();
+    assert!(!tree.root().is_last_child());
+    assert!(!b.is_last_child());
+    assert!(!b.next_sibling().unwrap().is_last_child());
+    assert!(tree.root().last_child().unwrap().is_last_child());
+    assert!(b.first_child().unwrap().is_last_child());
+}
+
+#[test]
+fn has_ancestor() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let b = tree.root().first_child().unwrap();
+    let c = b.first_child().unwrap();
+    assert!(c.has_ancestor(tree.root().id()));
+    assert!(c.has_ancestor(b.id()));
+    assert!(!c.has_ancestor(tree.root().last_child().unwrap().id()));
+    assert!(!c.has_ancestor(c.id()));
+}
+
+#[test]
+fn is_descendant_of() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let b = tree.root().first_child().unwrap();
+    let c = b.first_child().unwrap();
+    let d = tree.root().last_child().unwrap();
+
+    assert!(c.is_descendant_of(b.id()));
+    assert!(c.is_descendant_of(tree.root().id()));
+    assert!(!c.is_descendant_of(c.id()));
+    assert!(!c.is_descendant_of(d.id()));
+}
+
+#[test]
+fn is_descendant_of_any() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'e' } }, 'd' => { 'f' } });
+    let b = tree.root().first_child().unwrap();
+    let c = b.first_child().unwrap();
+    let e = c.first_child().unwrap();
+    let d = tree.root().last_child().unwrap();
+    let f = d.first_child().unwrap();
+
+    assert!(e.is_descendant_of_any(&[b.id(), c.id()]));
+    assert!(f.is_descendant_of_any(&[b.id(), d.id()]));
+    assert!(!f.is_descendant_of_any(&[b.id(), c.id()]));
+    assert!(!b.is_descendant_of_any(&[b.id(), d.id()]));
+    assert!(!e.is_descendant_of_any(&[]));
+}
+
+#[test]
+fn num_siblings() {
+    let tree = tree!('a' => { 'b', 'c', 'd', 'e' });
+    let b = tree.root().first_child().unwrap();
+    let c = b.next_sibling().unwrap();
+    let e = tree.root().last_child().unwrap();
+
+    assert_eq!((0, 3), (b.num_preceding_siblings(), b.num_following_siblings()));
+    assert_eq!((1, 2), (c.num_preceding_siblings(), c.num_following_siblings()));
+    assert_eq!((3, 0), (e.num_preceding_siblings(), e.num_following_siblings()));
+    assert_eq!(
+        (0, 0),
+        (tree.root().num_preceding_siblings(), tree.root().num_following_siblings())
+    );
+}
+
+#[test]
+fn group_children_by() {
+    let tree = tree!(0 => { 1, 2, 3 => { 4 }, 6 });
+    let groups = tree.root().group_children_by(|n| n % 2 == 0);
+    let values = |ids: &Vec<_>| ids.iter().map(|&id| *tree.get(id).value()).collect::<Vec<_>>();
+
+    assert_eq!(2, groups.len());
+    assert_eq!(vec![2, 6], values(&groups[&true]));
+    assert_eq!(vec![1, 3], values(&groups[&false]));
+}
+
+#[test]
+fn first_leaf() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd', 'e' }, 'f' }, 'g' => { 'h' } });
+    let b = tree.root().first_child().unwrap();
+    let g = tree.root().last_child().unwrap();
+    assert_eq!(&'d', tree.root().first_leaf().value());
+    assert_eq!(&'h', g.first_leaf().value());
+    let f = b.last_child().unwrap();
+    assert_eq!(f, f.first_leaf());
+}
+
+#[test]
+fn last_leaf() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd', 'e' }, 'f' }, 'g' => { 'h' } });
+    let b = tree.root().first_child().unwrap();
+    assert_eq!(&'h', tree.root().last_leaf().value());
+    assert_eq!(&'f', b.last_leaf().value());
+    assert_eq!(&'e', b.first_child().unwrap().last_leaf().value());
+    let d = b.first_child().unwrap().first_child().unwrap();
+    assert_eq!(d, d.last_leaf());
+}
+
+#[test]
+fn subtree_len() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' });
+    assert_eq!(5, tree.root().subtree_len());
+    assert_eq!(3, tree.root().first_child().unwrap().subtree_len());
+    assert_eq!(1, tree.root().last_child().unwrap().subtree_len());
+}
+
+#[test]
+fn height() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' });
+    let b = tree.root().first_child().unwrap();
+    assert_eq!(0, tree.root().last_child().unwrap().height());
+    assert_eq!(2, b.height());
+    assert_eq!(3, tree.root().height());
+    assert_eq!(tree.height_of(b.id()), b.height());
+}
+
+#[test]
+fn min_depth() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' => { 'f', 'g' => { 'h' } } });
+    let b = tree.root().first_child().unwrap();
+    assert_eq!(2, tree.root().min_depth());
+    assert_eq!(2, b.min_depth());
+    assert_eq!(1, tree.root().last_child().unwrap().min_depth());
+    assert_eq!(0, b.first_child().unwrap().first_child().unwrap().min_depth());
+}
+
+#[test]
+fn is_balanced() {
+    let tree = tree!('a' => {
+        'b' => { 'c' => { 'd' }, 'e' },
+        'f' => { 'g' => { 'h' => { 'i' } }, 'j' },
+    });
+    let b = tree.root().first_child().unwrap();
+    let f = tree.root().last_child().unwrap();
+    assert!(b.is_balanced());
+    assert!(!f.is_balanced());
+    assert!(!tree.root().is_balanced());
+    assert!(!f.first_child().unwrap().is_balanced());
+    assert!(f.first_child().unwrap().first_child().unwrap().is_balanced());
+    assert!(b.first_child().unwrap().is_balanced());
+    assert!(b.last_child().unwrap().is_balanced());
+}
+
+#[test]
+fn max_width() {
+    let tree = tree!('a' => {
+        'b' => { 'c' => { 'd' } },
+        'e' => { 'f' => { 'g', 'h' }, 'i' => { 'j', 'k' }, 'l' },
+    });
+    let b = tree.root().first_child().unwrap();
+    let e = tree.root().last_child().unwrap();
+    assert_eq!(4, e.max_width());
+    assert_eq!(5, tree.root().max_width());
+    assert_eq!(1, b.max_width());
+    assert_eq!(1, e.last_child().unwrap().max_width());
+}
+
+#[test]
+fn depth_range() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' => { 'f', 'g' => { 'h' } } });
+    let b = tree.root().first_child().unwrap();
+    assert_eq!((2, 3), tree.root().depth_range());
+    assert_eq!((2, 2), b.depth_range());
+    assert_eq!((1, 2), tree.root().last_child().unwrap().depth_range());
+    assert_eq!((0, 0), b.first_child().unwrap().first_child().unwrap().depth_range());
+}
+
+#[test]
+fn ids_by_level() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e', 'f' => { 'g', 'h' } });
+    let values = |levels: Vec<Vec<ego_tree::NodeId<char>>>| {
+        levels.into_iter()
+            .map(|level| level.into_iter().map(|id| *tree.get(id).value()).collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["a", "bef", "cgh", "d"], values(tree.root().ids_by_level()));
+    assert_eq!(vec!["f", "gh"], values(tree.root().last_child().unwrap().ids_by_level()));
+}
+
+#[test]
+fn longest_path_balanced() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' }, 'e' => { 'f', 'g' } });
+    let path = tree.root().longest_path();
+
+    assert_eq!(tree.root().height() + 1, path.len());
+    assert_eq!(
+        vec![&'a', &'b', &'c'],
+        path.into_iter().map(|id| tree.get(id).value()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn longest_path_unbalanced() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd' => { 'e' => { 'f' } }, 'g' });
+    let path = tree.root().longest_path();
+
+    assert_eq!(tree.root().height() + 1, path.len());
+    assert_eq!(
+        vec![&'a', &'d', &'e', &'f'],
+        path.into_iter().map(|id| tree.get(id).value()).collect::<Vec<_>>()
+    );
+    let g = tree.root().last_child().unwrap();
+    assert_eq!(vec![g.id()], g.longest_path());
+}
+
+#[test]
+fn ancestor() {
+    let tree = tree!('a' => { 'b' => { 'c' } });
+    let b = tree.root().first_child().unwrap();
+    let c = b.first_child().unwrap();
+
+    assert_eq!(Some(c), c.ancestor(0));
+    assert_eq!(Some(b), c.ancestor(1));
+    assert_eq!(Some(tree.root()), c.ancestor(2));
+    assert_eq!(None, c.ancestor(3));
+}
+
+#[test]
+fn preorder_offset() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' });
+    let b = tree.root().first_child().unwrap();
+    let e = b.last_child().unwrap().first_child().unwrap();
+    let f = tree.root().last_child().unwrap();
+
+    assert_eq!(Some(0), b.preorder_offset(b.id()));
+    assert_eq!(Some(1), b.preorder_offset(b.first_child().unwrap().id()));
+    assert_eq!(Some(3), b.preorder_offset(e.id()));
+    assert_eq!(None, b.preorder_offset(f.id()));
+    assert_eq!(None, b.preorder_offset(tree.root().id()));
+}
+
+#[test]
+fn next_pre_order() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' => { 'f' }, 'g' });
+    let mut values = Vec::new();
+    let mut node = Some(tree.root());
+    while let Some(current) = node {
+        values.push(*current.value());
+        node = current.next_pre_order();
+    }
+    assert_eq!(vec!['a', 'b', 'c', 'd', 'e', 'f', 'g'], values);
+}
+
+#[test]
+fn prev_pre_order() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' => { 'f' }, 'g' });
+    let mut values = Vec::new();
+    let mut node = tree.root().last_child();
+    while let Some(current) = node {
+        values.push(*current.value());
+        node = current.prev_pre_order();
+        if let Some(prev) = node {
+            assert_eq!(Some(current), prev.next_pre_order());
+        }
+    }
+    assert_eq!(vec!['g', 'f', 'e', 'd', 'c', 'b', 'a'], values);
+}
+
+#[test]
+fn preorder_nth() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' }, 'f' });
+    let b = tree.root().first_child().unwrap();
+    assert_eq!(Some(b), b.preorder_nth(0));
+    assert_eq!(Some(&'d'), b.preorder_nth(2).map(|n| n.value()));
+    assert_eq!(Some(&'e'), b.preorder_nth(3).map(|n| n.value()));
+    assert_eq!(None, b.preorder_nth(4));
+    assert_eq!(Some(&'f'), tree.root().preorder_nth(5).map(|n| n.value()));
+}
+
+#[test]
+fn to_owned() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' });
+    let b = tree.root().first_child().unwrap();
+    let owned = b.to_owned();
+    assert_eq!(tree!('b' => { 'c', 'd' => { 'e' } }), owned);
+    assert_eq!(b.to_sexpr(), owned.root().to_sexpr());
+    assert_eq!(tree, tree.root().to_owned());
+}
+
+#[test]
+fn clone_subtree_to_depth() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' => { 'g' } });
+    let b = tree.root().first_child().unwrap();
+    assert_eq!(tree!('a'), tree.root().clone_subtree_to_depth(0));
+    assert_eq!("(a b f)", tree.root().clone_subtree_to_depth(1).root().to_sexpr());
+    assert_eq!("(b c (d e))", b.clone_subtree_to_depth(2).root().to_sexpr());
+    assert_eq!(tree.root().to_sexpr(), tree.root().clone_subtree_to_depth(10).root().to_sexpr());
+}
+
+#[test]
+fn to_edge_list() {
+    let tree = tree!('a' => { 'x', 'b' => { 'c' => { 'd' }, 'e' } });
+    let b = tree.root().last_child().unwrap();
+    let (values, edges) = b.to_edge_list();
+    assert_eq!(vec![&'b', &'c', &'d', &'e'], values);
+    assert_eq!(vec![(0, 1), (1, 2), (0, 3)], edges);
+
+    let (values, edges) = b.last_child().unwrap().to_edge_list();
+    assert_eq!(vec![&'e'], values);
+    assert!(edges.is_empty());
+}
+
+#[test]
+fn map_subtree() {
+    let tree = tree!(1 => { 2 => { 3, 4 => { 5 } }, 6 });
+    let two = tree.root().first_child().unwrap();
+    let mapped = two.map_subtree(|n| n.to_string());
+    assert_eq!(
+        tree!(String::from("2") => { String::from("3"), String::from("4") => { String::from("5") } }),
+        mapped
+    );
+    assert!(tree!(0 => { 0, 0 => { 0 } }).structural_correspondence(&mapped).is_some());
+}
+
+#[test]
+fn leaf_values() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' }, 'f', 'g' => { 'h' } });
+    let leaves = tree.root()
+        .traverse()
+        .filter_map(|edge| match edge {
+            Edge::Open(node) if !node.has_children() => Some(node),
+            _ => None,
+        });
+    assert_eq!(
+        leaves.map(|n| n.value()).collect::<Vec<_>>(),
+        tree.root().leaf_values().collect::<Vec<_>>()
+    );
+    assert_eq!("defh", tree.root().leaf_values().collect::<String>());
+    assert_eq!("f", tree.root().children().nth(1).unwrap().leaf_values().collect::<String>());
+}
+
+#[test]
+fn descendant_values_vec() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' }, 'f' });
+    let mut expected = Vec::new();
+    for edge in tree.root().traverse().skip(1) {
+        if let Edge::Open(node) = edge {
+            expected.push(node.value());
+        }
+    }
+    assert_eq!(expected, tree.root().descendant_values_vec());
+    assert_eq!(vec![&'b', &'c', &'d', &'e', &'f'], tree.root().descendant_values_vec());
+    assert!(tree.root().last_child().unwrap().descendant_values_vec().is_empty());
+}
+
+#[test]
+fn filter_map_values() {
+    let tree = tree!(1 => { 2 => { 3, 4 }, 5, 6 });
+    let squares = tree.root().filter_map_values(|&n| if n % 2 == 0 { Some(n * n) } else { None });
+    assert_eq!(vec![4, 16, 36], squares);
+}
+
+#[test]
+fn try_for_each_value() {
+    let tree = tree!(0 => { 1 => { 2 }, 3 });
+    let mut seen = Vec::new();
+    let result: Result<(), i32> = tree.root().try_for_each_value(|&n| {
+        seen.push(n);
+        Ok(())
+    });
+    assert_eq!(Ok(()), result);
+    assert_eq!(vec![1, 2, 3], seen);
+}
+
+#[test]
+fn try_for_each_value_err() {
+    let tree = tree!(0 => { 1 => { 2 }, 3 });
+    let mut seen = Vec::new();
+    let result = tree.root().try_for_each_value(|&n| {
+        seen.push(n);
+        if n == 2 { Err(n) } else { Ok(()) }
+    });
+    assert_eq!(Err(2), result);
+    assert_eq!(vec![1, 2], seen);
+}
+
+#[test]
+fn walk() {
+    struct Recorder(Vec<String>);
+
+    impl Visitor<char> for Recorder {
+        fn enter(&mut self, node: NodeRef<char>) {
+            self.0.push(format!("+{}", node.value()));
+        }
+
+        fn leave(&mut self, node: NodeRef<char>) {
+            self.0.push(format!("-{}", node.value()));
+        }
+    }
+
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let mut recorder = Recorder(Vec::new());
+    tree.root().walk(&mut recorder);
+
+    assert_eq!(
+        vec!["+a", "+b", "+c", "-c", "-b", "+d", "-d", "-a"],
+        recorder.0
+    );
+}
+
+#[test]
+fn walk_default() {
+    struct Noop;
+    impl Visitor<char> for Noop { }
+
+    let tree = tree!('a' => { 'b' });
+    tree.root().walk(&mut Noop);
+}
+
+#[test]
+fn debug_pretty() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' }, 'f' });
+    let b = tree.root().first_child().unwrap();
+
+    assert_eq!(
+        "'b' => {\n    'c' => {\n        'd',\n    },\n    'e',\n}",
+        format!("{:?}", b.debug_pretty())
+    );
+    assert_eq!("'f'", format!("{:?}", tree.root().last_child().unwrap().debug_pretty()));
+}
+
+#[test]
+fn to_indented_lines() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' }, 'e' }, 'f' });
+    let b = tree.root().first_child().unwrap();
+    assert_eq!(vec!["b", "- c", "- - d", "- e"], b.to_indented_lines("- "));
+}
+
+#[test]
+fn to_sexpr() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' }, 'e' });
+    assert_eq!("(a (b c d) e)", tree.root().to_sexpr());
+    assert_eq!("(b c d)", tree.root().first_child().unwrap().to_sexpr());
+    assert_eq!("e", tree.root().last_child().unwrap().to_sexpr());
+
+    let tree = tree!("a b" => { "", "(c)", "say \"hi\"", "back\\slash" });
+    assert_eq!(
+        r#"("a b" "" "(c)" "say \"hi\"" "back\\slash")"#,
+        tree.root().to_sexpr()
+    );
+}
+
+#[test]
+fn structure_hash() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' }, 'x' => { 'b' => { 'c', 'd' } }, 'b' => { 'c' => { 'd' } } });
+    let first = tree.root().first_child().unwrap();
+    let nested = tree.root().children().nth(1).unwrap().first_child().unwrap();
+    let reshaped = tree.root().last_child().unwrap();
+    assert_eq!(first.structure_hash(), nested.structure_hash());
+    assert!(first.structure_hash() != reshaped.structure_hash());
+    assert!(first.structure_hash() != tree.root().structure_hash());
+}
+
+#[test]
+fn find_child() {
+    let tree = tree!('a' => { 'b', 'c' => { 'd' }, 'c' });
+    let root = tree.root();
+    assert_eq!(root.children().nth(1), root.find_child(&'c'));
+    assert_eq!(None, root.find_child(&'x'));
+    assert_eq!(None, root.find_child(&'d'));
+}
+
+#[test]
+fn path_values() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' });
+    let d = tree.root().first_child().unwrap().first_child().unwrap().first_child().unwrap();
+    assert_eq!(vec![&'a'], tree.root().path_values());
+    assert_eq!(vec![&'a', &'b', &'c', &'d'], d.path_values());
+}
+
+#[test]
+fn descendants_with_global_rank() {
+    let mut tree = tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' => { 'g' => { 'h' }, 'i' } });
+    let ranks = tree.label_pre_order();
+    for node in tree.nodes() {
+        for (rank, descendant) in node.descendants_with_global_rank() {
+            assert_eq!(ranks[&descendant.id()], rank);
+        }
+    }
+    let ranked = |node: NodeRef<char>| {
+        node.descendants_with_global_rank().map(|(r, n)| (r, *n.value())).collect::<Vec<_>>()
+    };
+    let g = tree.root().last_child().unwrap().first_child().unwrap();
+    assert_eq!(vec![(7, 'h')], ranked(g));
+
+    let orphan = tree.orphan('x').id();
+    let y = tree.get_mut(orphan).append('y').id();
+    let _ = tree.get_mut(y).append('z');
+    assert_eq!(vec![(1, 'y'), (2, 'z')], ranked(tree.get(orphan)));
+}
+
+#[test]
+fn path_values_to() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' });
+    let b = tree.root().first_child().unwrap();
+    let d = b.first_child().unwrap().first_child().unwrap().id();
+    let e = tree.root().last_child().unwrap().id();
+    assert_eq!(Some(vec![&'b', &'c', &'d']), b.path_values_to(d));
+    assert_eq!(Some(vec![&'a', &'e']), tree.root().path_values_to(e));
+    assert_eq!(Some(vec![&'b']), b.path_values_to(b.id()));
+    assert_eq!(None, b.path_values_to(e));
+    assert_eq!(None, b.path_values_to(tree.root().id()));
+}
+
 #[test]
 fn clone() {
     let tree = tree!('a');
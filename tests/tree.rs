@@ -1,6 +1,12 @@
+#[macro_use]
 extern crate ego_tree;
+#[cfg(feature = "json")]
+#[macro_use]
+extern crate serde_json;
 
-use ego_tree::Tree;
+use ego_tree::{Tree, BuildWarning, ParseError, Subtree, IdError, NodeId, VisitorMut, Links, ShapeMismatch};
+use ego_tree::{Inconsistency, InconsistencyKind};
+use ego_tree::iter::Edge;
 
 #[test]
 fn new() {
@@ -14,6 +20,34 @@ fn new() {
     assert_eq!(None, root.last_child());
 }
 
+#[test]
+fn new_with_id() {
+    let id = usize::MAX - 1;
+    let mut one = Tree::new_with_id('a', id);
+    let mut two = Tree::new_with_id('x', id);
+    let b = one.root_mut().append('b').id();
+    let y = two.root_mut().append('y').id();
+    assert_eq!(id, one.root().id().tree_id());
+
+    // Sharing an ID makes each tree accept the other's node IDs, resolving them to its own nodes.
+    assert_eq!(&'y', two.get(b).value());
+    assert_eq!(&'b', one.get(y).value());
+
+    let three = Tree::new_with_id('a', id - 1);
+    assert_eq!(Err(IdError::WrongTree), three.try_validate(b));
+    assert_eq!(Err(IdError::WrongTree), one.try_validate(three.root().id()));
+}
+
+#[test]
+#[should_panic(expected = "node ID is out of range")]
+fn new_with_id_shorter_tree() {
+    let id = usize::MAX - 2;
+    let mut big = Tree::new_with_id('a', id);
+    let c = big.root_mut().append('b').append('c').id();
+    let small = Tree::new_with_id('x', id);
+    let _ = small.get(c);
+}
+
 #[test]
 fn root() {
     let tree = Tree::new('a');
@@ -48,6 +82,35 @@ fn get_mut() {
     assert_eq!(&'a', tree.get_mut(id).value());
 }
 
+#[test]
+fn with_estimated() {
+    let tree = Tree::with_estimated('a', 3, 2);
+    assert!(tree.capacity() >= 1 + 3 + 9);
+    assert_eq!(&'a', tree.root().value());
+
+    assert!(Tree::with_estimated('a', 0, 5).capacity() >= 1);
+    assert!(Tree::with_estimated('a', 1, 5).capacity() >= 6);
+}
+
+#[test]
+fn with_estimated_large() {
+    let tree = Tree::with_estimated('a', 1000, 1000);
+    assert_eq!(1, tree.len());
+    let tree = Tree::with_estimated('a', 1, usize::MAX);
+    assert_eq!(1, tree.len());
+}
+
+#[test]
+fn len() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    assert_eq!(4, tree.len());
+    tree.orphan('e');
+    assert_eq!(5, tree.len());
+    let b_id = tree.root().first_child().unwrap().id();
+    let _ = tree.cut(&[b_id]);
+    assert_eq!(3, tree.len());
+}
+
 #[test]
 fn default() {
     let tree = Tree::<i32>::default();
@@ -75,3 +138,1192 @@ fn neq() {
     let two = Tree::new('b');
     assert_eq!(one, two);
 }
+
+#[test]
+fn eq_ignores_storage() {
+    let mut one = tree!('a' => { 'b' => { 'c' } });
+    let b = one.root().first_child().unwrap().id();
+    let _ = one.get_mut(b).drain_subtree();
+    assert_eq!(tree!('a'), one);
+
+    let mut two = Tree::new('a');
+    two.root_mut().append('c');
+    two.root_mut().prepend('b');
+    assert_eq!(tree!('a' => { 'b', 'c' }), two);
+}
+
+#[test]
+fn neq_orphans() {
+    let mut one = tree!('a' => { 'b' });
+    let two = tree!('a' => { 'b' });
+    let _ = one.orphan('c');
+    assert_ne!(one, two);
+}
+
+#[test]
+fn label_pre_order() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let labels = tree.label_pre_order();
+    let ids = tree.pre_order_ids();
+
+    assert_eq!(4, labels.len());
+    assert_eq!(0, labels[&tree.root().id()]);
+    for (label, id) in ids.iter().enumerate() {
+        assert_eq!(label, labels[id]);
+    }
+    assert_eq!(
+        vec![&'a', &'b', &'c', &'d'],
+        ids.into_iter().map(|id| tree.get(id).value()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn build_lenient_forward_reference() {
+    let rows = vec![('c', Some(1)), ('b', None), ('d', Some(1))];
+    let (tree, warnings) = Tree::build_lenient('a', rows);
+
+    let b = tree.root().first_child().unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(&'b', b.value());
+    assert!(b.is_only_child());
+    assert_eq!(vec![&'c', &'d'], b.children().map(|n| n.value()).collect::<Vec<_>>());
+}
+
+#[test]
+fn build_lenient_missing_parent() {
+    let rows = vec![('b', None), ('c', Some(5)), ('d', Some(3)), ('e', Some(2))];
+    let (tree, warnings) = Tree::build_lenient('a', rows);
+
+    assert_eq!(
+        vec![
+            BuildWarning::MissingParent { row: 1, parent: 5 },
+            BuildWarning::Cycle { row: 3, parent: 2 },
+        ],
+        warnings
+    );
+    assert_eq!(2, tree.pre_order_ids().len());
+    let orphans = tree.nodes().filter(|n| n.parent().is_none()).count();
+    assert_eq!(3, orphans);
+}
+
+#[test]
+fn count_where_with_parent() {
+    let tree = tree!('a' => { 'b' => { 'x', 'y' }, 'c' => { 'x' }, 'x' });
+    assert_eq!(2, tree.count_where_with_parent(|_, parent| parent == Some(&'b')));
+    assert_eq!(3, tree.count_where_with_parent(|&value, _| value == 'x'));
+    assert_eq!(1, tree.count_where_with_parent(|_, parent| parent.is_none()));
+}
+
+#[test]
+fn structural_correspondence() {
+    let one = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let two = tree!(1 => { 2 => { 3 }, 4 });
+    let pairs = one.structural_correspondence(&two).unwrap();
+
+    assert_eq!(4, pairs.len());
+    for (a, b) in pairs {
+        let a = one.get(a).value();
+        let b = two.get(b).value();
+        assert_eq!(*a as u32 - 'a' as u32 + 1, *b);
+    }
+}
+
+#[test]
+fn structural_correspondence_mismatch() {
+    let one = tree!('a' => { 'b' => { 'c' }, 'd' });
+    assert!(one.structural_correspondence(&tree!(1 => { 2, 3, 4 })).is_none());
+    assert!(one.structural_correspondence(&tree!(1 => { 2 => { 3 } })).is_none());
+    assert!(one.structural_correspondence(&tree!(1)).is_none());
+}
+
+#[test]
+fn shape_mismatch() {
+    let one = tree!('a' => { 'b' => { 'c' }, 'd' => { 'e', 'f' } });
+    assert_eq!(None, one.shape_mismatch(&tree!(1 => { 2 => { 3 }, 4 => { 5, 6 } })));
+    assert_eq!(
+        Some(ShapeMismatch { path: vec![1], expected_children: 2, actual_children: 3 }),
+        one.shape_mismatch(&tree!(1 => { 2 => { 3 }, 4 => { 5, 6, 7 } }))
+    );
+    assert_eq!(
+        Some(ShapeMismatch { path: vec![0, 0], expected_children: 0, actual_children: 1 }),
+        one.shape_mismatch(&tree!(1 => { 2 => { 3 => { 8 } }, 4 }))
+    );
+    assert_eq!(
+        Some(ShapeMismatch { path: vec![], expected_children: 2, actual_children: 0 }),
+        one.shape_mismatch(&tree!(1))
+    );
+}
+
+#[test]
+fn cut() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd', 'e' => { 'f', 'g' } });
+    let b_id = tree.root().first_child().unwrap().id();
+    let e_id = tree.root().last_child().unwrap().id();
+    let trees = tree.cut(&[e_id, b_id]);
+
+    assert_eq!(2, trees.len());
+    assert_eq!(tree!('e' => { 'f', 'g' }), trees[0]);
+    assert_eq!(tree!('b' => { 'c' }), trees[1]);
+    assert_eq!(tree!('a' => { 'd' }), tree);
+    assert_eq!(vec![&'a', &'d'], tree.values().collect::<Vec<_>>());
+}
+
+#[test]
+fn cut_nested() {
+    let mut tree = tree!('a' => { 'b' => { 'c' => { 'd' } } });
+    let b_id = tree.root().first_child().unwrap().id();
+    let c_id = tree.get(b_id).first_child().unwrap().id();
+    let trees = tree.cut(&[b_id, c_id]);
+
+    assert_eq!(tree!('b'), trees[0]);
+    assert_eq!(tree!('c' => { 'd' }), trees[1]);
+    assert!(!tree.root().has_children());
+}
+
+#[test]
+#[should_panic(expected = "cannot cut a node twice")]
+fn cut_twice() {
+    let mut tree = tree!('a' => { 'b' => { 'c' } });
+    let b_id = tree.root().first_child().unwrap().id();
+    let _ = tree.cut(&[b_id, b_id]);
+}
+
+#[test]
+fn cut_repeated() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let b_id = tree.root().first_child().unwrap().id();
+    let d_id = tree.root().last_child().unwrap().id();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| tree.cut(&[d_id, b_id, d_id])));
+
+    assert!(result.is_err());
+    assert_eq!(tree!('a' => { 'b' => { 'c' }, 'd' }), tree);
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+#[should_panic]
+fn cut_removed() {
+    let mut tree = tree!('a' => { 'b' });
+    let b_id = tree.root().first_child().unwrap().id();
+    let _ = tree.cut(&[b_id]);
+    tree.get(b_id);
+}
+
+#[test]
+fn update_values() {
+    use std::collections::HashMap;
+
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let c = tree.root().first_child().unwrap().first_child().unwrap().id();
+    let _ = tree.get_mut(c).drain_subtree();
+    let e = tree.orphan('e').id();
+
+    let replacements: HashMap<NodeId<char>, char> = tree.nodes()
+        .map(|node| (node.id(), node.value().to_ascii_uppercase()))
+        .collect();
+    let mut seen = Vec::new();
+    tree.update_values(|id, value| {
+        seen.push(id);
+        *value = replacements[&id];
+    });
+
+    assert_eq!(tree.nodes().map(|n| n.id()).collect::<Vec<_>>(), seen);
+    assert_eq!("(A B D)", tree.root().to_sexpr());
+    assert_eq!(&'E', tree.get(e).value());
+}
+
+#[test]
+fn map_leaves() {
+    let mut tree = tree!(1 => { 2 => { 3, 4 }, 5 });
+    tree.map_leaves(|value| value * 10);
+    assert_eq!(tree!(1 => { 2 => { 30, 40 }, 50 }), tree);
+}
+
+#[test]
+fn shared_ancestor_path_siblings() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' }, 'e' });
+    let b = tree.root().first_child().unwrap();
+    let c = b.first_child().unwrap();
+    let d = b.last_child().unwrap();
+
+    assert_eq!(vec![tree.root().id(), b.id()], tree.shared_ancestor_path(c.id(), d.id()));
+}
+
+#[test]
+fn shared_ancestor_path_parent_child() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' }, 'e' });
+    let b = tree.root().first_child().unwrap();
+    let d = b.last_child().unwrap();
+
+    assert_eq!(vec![tree.root().id(), b.id()], tree.shared_ancestor_path(b.id(), d.id()));
+    assert_eq!(vec![tree.root().id(), b.id()], tree.shared_ancestor_path(d.id(), b.id()));
+}
+
+#[test]
+fn is_path() {
+    assert!(tree!('a' => { 'b' => { 'c' => { 'd' } } }).is_path());
+    assert!(tree!('a').is_path());
+    assert!(!tree!('a' => { 'b' => { 'c', 'd' } }).is_path());
+}
+
+#[test]
+fn descendants_within() {
+    let tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' => { 'f' } });
+    let values = |depth| tree.descendants_within(depth).map(|n| *n.value()).collect::<String>();
+    assert_eq!("a", values(0));
+    assert_eq!("abe", values(1));
+    assert_eq!("abcef", values(2));
+    assert_eq!("abcdef", values(5));
+}
+
+#[test]
+fn values_post_order() {
+    let mut tree = tree!('a' => { 'b', 'c' => { 'd' } });
+    assert_eq!(vec![&'b', &'d', &'c', &'a'], tree.values_post_order().collect::<Vec<_>>());
+
+    let _ = tree.orphan('e');
+    assert_eq!("bdca", tree.values_post_order().collect::<String>());
+}
+
+#[test]
+fn orphan_ids() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    assert!(tree.orphan_ids().is_empty());
+
+    let e = tree.orphan('e').id();
+    let f = tree.get_mut(e).append('f').id();
+    let b = tree.root().first_child().unwrap().id();
+    tree.get_mut(b).detach();
+    let c = tree.get(b).first_child().unwrap().id();
+    let _ = tree.root_mut().append('g');
+
+    assert_eq!(vec![b, c, e, f], tree.orphan_ids());
+}
+
+#[test]
+fn attach_all_orphans() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let e = tree.orphan('e').id();
+    let _ = tree.get_mut(e).append('f');
+    let b = tree.root().first_child().unwrap().id();
+    tree.get_mut(b).detach();
+    let g = tree.orphan('g').id();
+    let d = tree.root().last_child().unwrap().id();
+
+    tree.attach_all_orphans(d);
+    assert!(tree.orphan_ids().is_empty());
+    assert_eq!("(a (d (b c) (e f) g))", tree.root().to_sexpr());
+    assert_eq!(Some(tree.get(d)), tree.get(g).parent());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+#[should_panic(expected = "cannot attach orphans below an unreachable node")]
+fn attach_all_orphans_below_orphan() {
+    let mut tree = tree!('a');
+    let b = tree.orphan('b').append('c').id();
+    tree.attach_all_orphans(b);
+}
+
+#[test]
+fn rebuild_in_preorder() {
+    let mut tree = tree!('a' => { 'b' });
+    let d = tree.root_mut().append('d').id();
+    let c = tree.get_mut(d).insert_before('c').id();
+    let _ = tree.get_mut(c).append('x').id();
+    let e = tree.orphan('e').id();
+    let doomed = tree.root_mut().append('z').id();
+    let _ = tree.get_mut(doomed).drain_subtree();
+    let old_ids = tree.nodes().map(|n| (n.id(), *n.value())).collect::<Vec<_>>();
+
+    let ids = tree.rebuild_in_preorder();
+    assert_eq!(old_ids.len(), ids.len());
+    for (old, value) in old_ids {
+        assert_eq!(&value, tree.get(ids[&old]).value());
+    }
+    assert_eq!(
+        (0..5).collect::<Vec<_>>(),
+        tree.pre_order_ids().into_iter().map(|id| tree.index_of(id).unwrap()).collect::<Vec<_>>()
+    );
+    assert_eq!(Some(5), tree.index_of(ids[&e]));
+    assert_eq!("(a b (c x) d)", tree.root().to_sexpr());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn canonicalize() {
+    let mut one = tree!('a' => { 'c' => { 'e', 'd' }, 'b' });
+    let mut two = tree!('a' => { 'b', 'c' => { 'd', 'e' } });
+    assert!(one != two);
+
+    one.canonicalize();
+    two.canonicalize();
+    assert_eq!(one, two);
+    assert_eq!(tree!('a' => { 'b', 'c' => { 'd', 'e' } }), one);
+}
+
+#[test]
+fn canonicalize_equal_siblings() {
+    let mut one = tree!(0 => { 5 => { 1 }, 5 => { 2 } });
+    let mut two = tree!(0 => { 5 => { 2 }, 5 => { 1 } });
+    one.canonicalize();
+    two.canonicalize();
+    assert_eq!(one, two);
+
+    let mut three = tree!(0 => { 5 => { 1 => { 9 } }, 5 => { 1 }, 5 });
+    let mut four = tree!(0 => { 5, 5 => { 1 }, 5 => { 1 => { 9 } } });
+    three.canonicalize();
+    four.canonicalize();
+    assert_eq!(three, four);
+    assert_eq!("(0 5 (5 1) (5 (1 9)))", three.root().to_sexpr());
+}
+
+#[test]
+#[should_panic]
+fn canonicalize_invalidates_ids() {
+    let mut tree = tree!('a' => { 'b' });
+    let id = tree.root().id();
+    tree.canonicalize();
+    tree.get(id);
+}
+
+#[test]
+fn map_ref_with_path() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' });
+    let lengths = tree.map_ref_with_path(|path, _| path.len());
+    assert_eq!(tree!(0 => { 1 => { 2, 2 => { 3 } }, 1 }), lengths);
+
+    let paths = tree.map_ref_with_path(|path, value| format!("{}{:?}", value, path));
+    assert_eq!(
+        vec!["a[]", "b[0]", "c[0, 0]", "d[0, 1]", "e[0, 1, 0]", "f[1]"],
+        paths.values().map(String::as_str).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn contains() {
+    let tree = tree!('a' => { 'b' => { 'c' } });
+    assert!(tree.contains(&'a'));
+    assert!(tree.contains(&'c'));
+    assert!(!tree.contains(&'d'));
+}
+
+#[test]
+fn find_by_value_path() {
+    let tree = tree!('a' => { 'b' => { 'c' }, 'b' => { 'd' }, 'e' => { 'f' => { 'g' } } });
+    let g = tree.root().last_child().unwrap().first_child().unwrap().first_child().unwrap();
+    assert_eq!(Some(g), tree.find_by_value_path(&['e', 'f', 'g']));
+    assert_eq!(Some(tree.root()), tree.find_by_value_path(&[]));
+    assert_eq!(Some(&'c'), tree.find_by_value_path(&['b', 'c']).map(|n| n.value()));
+    assert_eq!(None, tree.find_by_value_path(&['b', 'd']));
+    assert_eq!(None, tree.find_by_value_path(&['x', 'f']));
+    assert_eq!(None, tree.find_by_value_path(&['e', 'x', 'g']));
+}
+
+#[test]
+fn as_ref() {
+    let tree = tree!(String::from("a") => { String::from("b") => { String::from("c") } });
+    let refs = tree.as_ref();
+
+    assert!(tree.structural_correspondence(&refs).is_some());
+    for (a, b) in tree.values().zip(refs.values()) {
+        assert!(std::ptr::eq(a, *b));
+    }
+}
+
+#[test]
+fn map() {
+    let tree = tree!(1 => { 2, 3 => { 4 } });
+    assert_eq!(tree!(10 => { 20, 30 => { 40 } }), tree.map(|n| n * 10));
+}
+
+#[test]
+fn map_indexed() {
+    let mut tree = tree!('a' => { 'b', 'c' => { 'd' } });
+    let _ = tree.orphan('e');
+    let indexes = tree.map_indexed(|index, _| index);
+    assert_eq!(vec![&0, &1, &2, &3, &4], indexes.values().collect::<Vec<_>>());
+    assert_eq!(&2, indexes.root().last_child().unwrap().value());
+}
+
+#[test]
+fn traverse_inherited() {
+    let tree = tree!(1 => { 2 => { 3, 4 }, 10 => { 1 } });
+    assert_eq!(
+        tree!(1 => { 3 => { 6, 7 }, 11 => { 12 } }),
+        tree.traverse_inherited(0, |&n, &sum| sum + n)
+    );
+    assert_eq!(tree!(105), tree!(5).traverse_inherited(100, |&n, &sum| sum + n));
+}
+
+#[test]
+fn weighted_height() {
+    let tree = tree!(1 => { 2 => { 3, 4 }, 10, 1 => { 1 => { 1 } } });
+    assert_eq!(11, tree.weighted_height(|&n| n));
+    assert_eq!(4, tree.weighted_height(|_| 1));
+    assert_eq!(5, tree!(5).weighted_height(|&n| n));
+}
+
+#[test]
+fn value_counts() {
+    let tree = tree!('a' => { 'b' => { 'a', 'c' }, 'b', 'a' });
+    let counts = tree.value_counts();
+    assert_eq!(3, counts.len());
+    assert_eq!(3, counts[&'a']);
+    assert_eq!(2, counts[&'b']);
+    assert_eq!(1, counts[&'c']);
+}
+
+#[test]
+fn enumerate_values() {
+    let mut tree = tree!(9 => { 9 => { 9, 9 }, 9 => { 9 } });
+    let orphan = tree.orphan(9).id();
+    let _ = tree.root_mut().first_child().unwrap().prepend(9);
+    tree.enumerate_values();
+
+    assert_eq!("(0 (1 2 3 4) (5 6))", tree.root().to_sexpr());
+    for (label, id) in tree.pre_order_ids().into_iter().enumerate() {
+        assert_eq!(&label, tree.get(id).value());
+    }
+    assert_eq!(&9, tree.get(orphan).value());
+}
+
+#[test]
+fn mode() {
+    let tree = tree!('a' => { 'b' => { 'c', 'b' }, 'c', 'b' });
+    assert_eq!(Some(&'b'), tree.mode());
+    let tree = tree!('a' => { 'b' => { 'c', 'b' }, 'c' });
+    assert_eq!(Some(&'b'), tree.mode());
+    assert_eq!(Some(&'a'), tree!('a').mode());
+}
+
+#[test]
+fn prune_childless_matching() {
+    let mut tree = tree!("" => { "a", "" => { "" => { "" } }, "" => { "b", "" } });
+    tree.prune_childless_matching(|s| s.is_empty());
+
+    assert_eq!(tree!("" => { "a", "" => { "b" } }), tree);
+    assert_eq!(4, tree.len());
+}
+
+#[test]
+fn prune_childless_matching_root() {
+    let mut tree = tree!("" => { "" });
+    tree.prune_childless_matching(|s| s.is_empty());
+    assert_eq!(1, tree.len());
+}
+
+#[test]
+fn sanity_check() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    tree.orphan('e').append('f');
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn swap_with() {
+    let mut one = tree!('a' => { 'b', 'c' => { 'd' }, 'e' });
+    let mut two = tree!('v' => { 'w' => { 'x', 'y' }, 'z' });
+    let c_id = one.root().first_child().unwrap().next_sibling().unwrap().id();
+    let w_id = two.root().first_child().unwrap().id();
+    let (new_w_id, new_c_id) = one.swap_with(c_id, &mut two, w_id);
+
+    one.sanity_check().unwrap();
+    two.sanity_check().unwrap();
+    assert_eq!(tree!('a' => { 'b', 'w' => { 'x', 'y' }, 'e' }), one);
+    assert_eq!(tree!('v' => { 'c' => { 'd' }, 'z' }), two);
+    assert_eq!(&'w', one.get(new_w_id).value());
+    assert_eq!(&'c', two.get(new_c_id).value());
+    assert_eq!(6, one.len());
+    assert_eq!(4, two.len());
+}
+
+#[test]
+fn repair_children_ranges() {
+    let mut tree = tree!('a' => { 'b', 'c', 'd' });
+    let b_id = tree.root().first_child().unwrap().id();
+    let x_id = tree.orphan('x').id();
+
+    // Linking an attached node elsewhere leaves its old parent's range stale.
+    unsafe { tree.get_mut(x_id).append_id(b_id); }
+    let error = tree.sanity_check().unwrap_err();
+    assert_eq!(InconsistencyKind::WrongParent, error.kind);
+    assert_eq!(b_id, tree.get_by_index(error.node).unwrap().id());
+
+    tree.repair_children_ranges();
+    tree.sanity_check().unwrap();
+    assert_eq!(
+        vec![&'c', &'d'],
+        tree.root().children().map(|n| n.value()).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![&'b'],
+        tree.get(x_id).children().map(|n| n.value()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn leaf_count() {
+    let tree = tree!(0 => { 1 => { 3, 4 }, 2 => { 5, 6 } });
+    assert_eq!(4, tree.leaf_count());
+    assert_eq!(1, tree!(0).leaf_count());
+}
+
+#[test]
+fn total_path_length() {
+    let tree = tree!(0 => { 1 => { 3, 4 }, 2 => { 5, 6 } });
+    assert_eq!(8, tree.total_path_length());
+    assert_eq!(0, tree!(0).total_path_length());
+}
+
+#[test]
+fn from_sexpr() {
+    let tree = Tree::from_sexpr("(a (b) (c d e))").unwrap();
+    assert_eq!("(a b (c d e))", tree.root().to_sexpr());
+    assert_eq!(vec!["a", "b", "c", "d", "e"], tree.values().map(String::as_str).collect::<Vec<_>>());
+    assert_eq!("x", Tree::from_sexpr("  x ").unwrap().root().value());
+}
+
+#[test]
+fn from_sexpr_round_trip() {
+    let tree = tree!(String::from("a b") => {
+        String::from("(c)") => { String::from(""), String::from("say \"hi\"") },
+        String::from("back\\slash"),
+    });
+    let sexpr = tree.root().to_sexpr();
+    assert_eq!(sexpr, Tree::from_sexpr(&sexpr).unwrap().root().to_sexpr());
+
+    let sexpr = "(a (b c d) e)";
+    assert_eq!(sexpr, Tree::from_sexpr(sexpr).unwrap().root().to_sexpr());
+}
+
+#[test]
+fn from_sexpr_errors() {
+    assert_eq!(Err(ParseError::ExpectedValue { offset: 2 }), Tree::from_sexpr("  "));
+    assert_eq!(Err(ParseError::EmptyParens { offset: 3 }), Tree::from_sexpr("(a () b)"));
+    assert_eq!(Err(ParseError::UnclosedParen { offset: 3 }), Tree::from_sexpr("(a (b c"));
+    assert_eq!(Err(ParseError::UnopenedParen { offset: 5 }), Tree::from_sexpr("(a b))"));
+    assert_eq!(Err(ParseError::UnclosedQuote { offset: 3 }), Tree::from_sexpr("(a \"b)"));
+    assert_eq!(Err(ParseError::TrailingInput { offset: 6 }), Tree::from_sexpr("(a b) c"));
+    assert_eq!(Err(ParseError::ExpectedValue { offset: 1 }), Tree::from_sexpr("((a) b)"));
+}
+
+#[test]
+fn attach_subtree() {
+    let mut subtree = Subtree::new('b');
+    let c = subtree.append(0, 'c');
+    subtree.append_all(c, vec!['d', 'e']);
+    let _ = subtree.append(0, 'f');
+
+    let mut tree = tree!('a' => { 'x' });
+    let root = tree.root().id();
+    let b = tree.attach_subtree(root, subtree);
+
+    assert_eq!(tree!('a' => { 'x', 'b' => { 'c' => { 'd', 'e' }, 'f' } }), tree);
+    assert_eq!(&'b', tree.get(b).value());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn cut_below() {
+    let mut tree = tree!('a' => { 'b' => { '*' => { 'c' => { 'd' } }, 'e' }, '*' => { '*' => { 'f' } } });
+    let mut visited = Vec::new();
+    tree.cut_below(|&c| { visited.push(c); c == '*' });
+
+    assert_eq!(vec!['a', 'b', '*', 'e', '*'], visited);
+    assert_eq!("(a (b * e) *)", tree.root().to_sexpr());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn clone_subtrees() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' => { 'g' }, 'h' });
+    let b = tree.root().first_child().unwrap().id();
+    let f = tree.root().children().nth(1).unwrap().id();
+    let h = tree.root().last_child().unwrap().id();
+
+    let clones = tree.clone_subtrees(&[f, b, h]);
+    assert_eq!(3, clones.len());
+    assert_eq!(tree!('f' => { 'g' }), clones[0]);
+    assert_eq!(tree!('b' => { 'c', 'd' => { 'e' } }), clones[1]);
+    assert_eq!(tree!('h'), clones[2]);
+}
+
+#[test]
+fn detach_matching() {
+    let mut tree = tree!('a' => {
+        'b' => { '*' => { 'c', '*' => { 'd' } }, 'e' },
+        '*' => { 'f' },
+        'g',
+    });
+    let detached = tree.detach_matching(|&c| c == '*');
+
+    assert_eq!(2, detached.len());
+    assert_eq!("(a (b e) g)", tree.root().to_sexpr());
+    assert_eq!("(* c (* d))", tree.get(detached[0]).to_sexpr());
+    assert_eq!("(* f)", tree.get(detached[1]).to_sexpr());
+    assert!(detached.iter().all(|&id| tree.get(id).parent().is_none()));
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn drain_matching() {
+    let mut tree = tree!('a' => {
+        'b' => { '*' => { 'c', '*' => { 'd' } }, 'e' },
+        '*' => { 'f' },
+        'g',
+    });
+    let trees = tree.drain_matching(|&c| c == '*');
+
+    assert_eq!(2, trees.len());
+    assert_eq!("(* c (* d))", trees[0].root().to_sexpr());
+    assert_eq!("(* f)", trees[1].root().to_sexpr());
+    assert!(trees.iter().all(|t| t.sanity_check().is_ok()));
+    assert_eq!("(a (b e) g)", tree.root().to_sexpr());
+    assert_eq!(4, tree.len());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn duplicate_subtrees() {
+    let tree = tree!('a' => {
+        'b' => { 'c', 'd' },
+        'x' => { 'b' => { 'c', 'd' }, 'e' },
+        'b' => { 'c' => { 'd' } },
+        'e',
+    });
+    let groups = tree.duplicate_subtrees()
+        .into_iter()
+        .map(|group| group.into_iter().map(|id| tree.get(id).to_sexpr()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            vec!["(b c d)", "(b c d)"],
+            vec!["c", "c"],
+            vec!["d", "d", "d"],
+            vec!["e", "e"],
+        ],
+        groups
+    );
+}
+
+#[test]
+fn duplicate_subtrees_chain() {
+    let mut tree = Tree::new(0);
+    let mut id = tree.root().id();
+    for _ in 0..20_000 {
+        id = tree.get_mut(id).append(0).id();
+    }
+    assert!(tree.duplicate_subtrees().is_empty());
+
+    let tree = tree!('x' => { 'y' => { 'a', 'b' }, 'y' => { 'b', 'a' }, 'y' => { 'a' => { 'b' } } });
+    assert!(tree.duplicate_subtrees().iter().all(|group| tree.get(group[0]).value() != &'y'));
+}
+
+#[test]
+fn try_validate() {
+    let mut tree = tree!('a' => { 'b' => { 'c' } });
+    let b = tree.root().first_child().unwrap().id();
+    let c = tree.get(b).first_child().unwrap().id();
+    assert_eq!(Ok(tree.get(b)), tree.try_validate(b));
+
+    let _ = tree.get_mut(c).drain_subtree();
+    assert_eq!(Err(IdError::Removed), tree.try_validate(c));
+
+    let other = tree!('a' => { 'b' });
+    let other_b = other.root().first_child().unwrap().id();
+    assert_eq!(Err(IdError::WrongTree), tree.try_validate(other_b));
+    assert_eq!(Err(IdError::WrongTree), other.try_validate(b));
+}
+
+#[test]
+fn coalesce_leaves() {
+    let s = String::from;
+    let mut tree = tree!(s("p") => {
+        s("a"),
+        s("b"),
+        s("c"),
+        s("div") => { s("d"), s("e") },
+        s("f"),
+        s("g") => { s("h"), s("!"), s("i"), s("j") },
+    });
+    let orphan = tree.orphan(s("q")).id();
+    let _ = tree.get_mut(orphan).append(s("k"));
+    let _ = tree.get_mut(orphan).append(s("l"));
+
+    tree.coalesce_leaves(|left, right| {
+        if left == "!" || right == "!" {
+            return false;
+        }
+        left.push_str(right);
+        true
+    });
+
+    assert_eq!("(p abc (div de) f (g h ! ij))", tree.root().to_sexpr());
+    assert_eq!("(q kl)", tree.get(orphan).to_sexpr());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn largest_subtree_by() {
+    let mut tree = tree!(1 => { 2 => { 3, 4 => { 5 } }, 6 => { 7 } });
+    assert_eq!(tree.root().id(), tree.largest_subtree_by(|n| n.subtree_len()));
+
+    let two = tree.root().first_child().unwrap().id();
+    assert_eq!(two, tree.largest_subtree_by(|n| n.value() % 2 == 0 && n.height() == 2));
+    assert_eq!(tree.root().id(), tree.largest_subtree_by(|n| n.children().count()));
+
+    let orphan = tree.orphan(0).id();
+    for i in 0..10 {
+        let _ = tree.get_mut(orphan).append(i);
+    }
+    assert_eq!(orphan, tree.largest_subtree_by(|n| n.subtree_len()));
+    let first_leaf = tree.get(two).first_child().unwrap().id();
+    assert_eq!(first_leaf, tree.largest_subtree_by(|n| !n.has_children()));
+}
+
+#[test]
+fn subtree_sizes() {
+    let mut tree = tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' });
+    let sizes = tree.subtree_sizes();
+    assert_eq!(tree.len(), sizes.len());
+    assert_eq!(tree.len(), sizes[&tree.root().id()]);
+    for node in tree.nodes() {
+        assert_eq!(node.subtree_len(), sizes[&node.id()]);
+    }
+
+    let orphan = tree.orphan('g').id();
+    let _ = tree.get_mut(orphan).append('h');
+    let sizes = tree.subtree_sizes();
+    assert_eq!(6, sizes[&tree.root().id()]);
+    assert_eq!(2, sizes[&orphan]);
+}
+
+#[test]
+fn eq_by() {
+    let a = tree!("Html" => { "Head", "BODY" => { "p" } });
+    let b = tree!("html" => { "head", "body" => { "P" } });
+    let c = tree!("html" => { "head" => { "body" => { "P" } } });
+    let ignore_case = |a: &&str, b: &&str| a.eq_ignore_ascii_case(b);
+
+    assert!(a != b);
+    assert!(a.eq_by(&b, ignore_case));
+    assert!(!a.eq_by(&c, ignore_case));
+    assert!(!a.eq_by(&b, |a, b| a == b));
+}
+
+#[test]
+fn depth_of() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let c = tree.root().first_child().unwrap().first_child().unwrap().id();
+    let orphan = tree.orphan('e').id();
+    assert_eq!(0, tree.depth_of(tree.root().id()));
+    assert_eq!(2, tree.depth_of(c));
+    assert_eq!(0, tree.depth_of(orphan));
+}
+
+#[test]
+fn preorder_windows() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let _ = tree.orphan('e').append('f');
+    assert_eq!(
+        vec![('a', 'b'), ('b', 'c'), ('c', 'd')],
+        tree.preorder_windows().map(|(l, r)| (*l.value(), *r.value())).collect::<Vec<_>>()
+    );
+    assert_eq!(0, Tree::new('a').preorder_windows().count());
+}
+
+#[test]
+fn iter_with_depth() {
+    let mut tree = tree!('a' => { 'b' => { 'c' => { 'd' } }, 'e' });
+    let orphan = tree.orphan('f').id();
+    let _ = tree.get_mut(orphan).append('g');
+    let root = tree.root().id();
+    let b = tree.root().first_child().unwrap().id();
+    // Move the subtree of `b` below a node created after it.
+    unsafe { let _ = tree.get_mut(orphan).append_id(b); }
+    let _ = tree.get_mut(root).append('h');
+
+    let scanned = tree.iter_with_depth().collect::<Vec<_>>();
+    assert_eq!(tree.len(), scanned.len());
+    for &(depth, id, value) in &scanned {
+        assert_eq!(tree.depth_of(id), depth);
+        assert_eq!(tree.get(id).value(), value);
+    }
+    assert_eq!(
+        vec![(0, 'a'), (1, 'b'), (2, 'c'), (3, 'd'), (1, 'e'), (0, 'f'), (1, 'g'), (1, 'h')],
+        scanned.into_iter().map(|(depth, _, &value)| (depth, value)).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn intersection() {
+    let tree = tree!('a' => { 'b' => { 'c', 'd' }, 'e' => { 'f' } });
+    assert_eq!("(a (b c d) (e f))", tree.intersection(&tree.clone()).unwrap().root().to_sexpr());
+
+    let other = tree!('a' => { 'b' => { 'c', 'x', 'd' }, 'e', 'g' });
+    assert_eq!("(a (b c) e)", tree.intersection(&other).unwrap().root().to_sexpr());
+    assert_eq!("(a (b c) e)", other.intersection(&tree).unwrap().root().to_sexpr());
+
+    let other = tree!('a' => { 'x' => { 'b' }, 'e' });
+    assert_eq!("a", tree.intersection(&other).unwrap().root().to_sexpr());
+    assert_eq!(None, tree.intersection(&tree!('x' => { 'b' => { 'c', 'd' } })));
+}
+
+#[test]
+fn fold_into_external() {
+    #[derive(Debug, PartialEq)]
+    enum Expr {
+        Num(i32),
+        Op(char, Vec<Expr>),
+    }
+
+    let mut tree = tree!("+" => { "1", "*" => { "2", "3" } });
+    let _ = tree.orphan("4");
+    let expr = tree.fold_into_external(|value, children| match value.parse() {
+        Ok(n) => Expr::Num(n),
+        Err(_) => Expr::Op(value.chars().next().unwrap(), children),
+    });
+    assert_eq!(
+        Expr::Op('+', vec![Expr::Num(1), Expr::Op('*', vec![Expr::Num(2), Expr::Num(3)])]),
+        expr
+    );
+
+    fn append(node: &mut ego_tree::NodeMut<String>, expr: &Expr) {
+        match *expr {
+            Expr::Num(n) => { let _ = node.append(n.to_string()); },
+            Expr::Op(op, ref args) => {
+                let mut child = node.append(op.to_string());
+                for arg in args {
+                    append(&mut child, arg);
+                }
+            },
+        }
+    }
+    let mut back = Tree::new(String::new());
+    append(&mut back.root_mut(), &expr);
+    assert_eq!("(\"\" (+ 1 (* 2 3)))", back.root().to_sexpr());
+}
+
+#[test]
+fn extract_subtree() {
+    let mut tree = tree!('a' => { 'b' => { 'c', 'd' => { 'e' } }, 'f' });
+    let b = tree.root().first_child().unwrap();
+    let expected = b.traverse().filter_map(|edge| match edge {
+        Edge::Open(node) => Some((node.id(), *node.value())),
+        Edge::Close(_) => None,
+    }).collect::<Vec<_>>();
+    let b = b.id();
+
+    let extracted = tree.extract_subtree(b);
+    assert_eq!(expected, extracted);
+    assert_eq!(vec!['b', 'c', 'd', 'e'], extracted.iter().map(|&(_, v)| v).collect::<Vec<_>>());
+    assert_eq!("(a f)", tree.root().to_sexpr());
+    assert_eq!(2, tree.len());
+    for (id, _) in extracted {
+        assert!(tree.try_validate(id).is_err());
+    }
+}
+
+#[test]
+#[should_panic(expected = "cannot remove the root")]
+fn extract_subtree_root() {
+    let mut tree = tree!('a' => { 'b' });
+    let root = tree.root().id();
+    let _ = tree.extract_subtree(root);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn to_json() {
+    let mut tree = tree!(1 => { 2 => { 3 }, 4 });
+    let _ = tree.orphan(5);
+    assert_eq!(
+        json!({
+            "value": "1",
+            "children": [
+                { "value": "2", "children": [{ "value": "3", "children": [] }] },
+                { "value": "4", "children": [] },
+            ],
+        }),
+        tree.to_json(|n| json!(n.to_string()))
+    );
+}
+
+#[test]
+fn balanced_binary_from() {
+    assert_eq!(tree!(1), Tree::balanced_binary_from(vec![1]));
+    assert_eq!(tree!(1 => { 2, 3 }), Tree::balanced_binary_from(1..4));
+    assert_eq!("(1 (2 4 5) (3 6 7))", Tree::balanced_binary_from(1..8).root().to_sexpr());
+
+    let tree = Tree::balanced_binary_from(0..20);
+    assert_eq!(20, tree.len());
+    assert!(tree.nodes().all(|node| node.children().count() <= 2));
+    assert_eq!(4, tree.root().height());
+}
+
+#[test]
+fn index_of() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    for node in tree.nodes() {
+        assert_eq!(Some(node), tree.index_of(node.id()).and_then(|i| tree.get_by_index(i)));
+    }
+    let other = tree!('a');
+    assert_eq!(None, tree.index_of(other.root().id()));
+
+    let c = tree.root().first_child().unwrap().first_child().unwrap().id();
+    let _ = tree.get_mut(c).drain_subtree();
+    assert_eq!(None, tree.index_of(c));
+}
+
+#[test]
+fn get_by_index() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    assert_eq!(Some(tree.root()), tree.get_by_index(0));
+    assert_eq!(Some(&'c'), tree.get_by_index(2).map(|n| n.value()));
+    assert_eq!(None, tree.get_by_index(4));
+
+    let c = tree.root().first_child().unwrap().first_child().unwrap().id();
+    let _ = tree.get_mut(c).drain_subtree();
+    assert_eq!(None, tree.get_by_index(2));
+}
+
+#[test]
+fn sum() {
+    let mut tree = tree!(1 => { -2 => { 3 }, 4 });
+    assert_eq!(6, tree.sum());
+    let _ = tree.orphan(10);
+    assert_eq!(16, tree.sum());
+}
+
+#[test]
+fn min_value() {
+    let tree = tree!(1 => { -2 => { 3 }, 4 });
+    assert_eq!(&-2, tree.min_value());
+    assert_eq!(&7, tree!(7).min_value());
+}
+
+#[test]
+fn max_value() {
+    let tree = tree!(1 => { -2 => { 3 }, 4 });
+    assert_eq!(&4, tree.max_value());
+    assert_eq!(&7, tree!(7).max_value());
+}
+
+#[test]
+fn fingerprint() {
+    let build = || tree!(String::from("a") => { String::from("b") => { String::from("c") }, String::from("d") });
+    let tree = build();
+    assert_eq!(tree.fingerprint(), build().fingerprint());
+    assert_eq!(tree.fingerprint(), tree.clone().fingerprint());
+
+    let reshaped = tree!(String::from("a") => { String::from("b"), String::from("c"), String::from("d") });
+    assert!(tree.fingerprint() != reshaped.fingerprint());
+    let renamed = tree!(String::from("a") => { String::from("b") => { String::from("x") }, String::from("d") });
+    assert!(tree.fingerprint() != renamed.fingerprint());
+
+    let mut with_orphan = build();
+    let _ = with_orphan.orphan(String::from("e"));
+    assert!(tree.fingerprint() != with_orphan.fingerprint());
+}
+
+#[test]
+fn fingerprint_is_fixed() {
+    let tree = tree!(String::from("a") => { String::from("b") });
+    assert_eq!(
+        [
+            126, 44, 172, 70, 216, 145, 96, 167, 78, 150, 82, 218, 144, 141, 138, 90,
+            7, 165, 43, 241, 167, 158, 4, 236, 207, 81, 215, 31, 164, 240, 201, 39,
+        ],
+        tree.fingerprint()
+    );
+}
+
+#[test]
+fn from_indented() {
+    let tree = Tree::from_indented("a\n  b\n    c\n\n  d  \n    e\n      f\n  g\n", 2).unwrap();
+    assert_eq!("(a (b c) (d (e f)) g)", tree.root().to_sexpr());
+}
+
+#[test]
+fn from_indented_round_trip() {
+    let tree = tree!(String::from("a") => {
+        String::from("b") => { String::from("c"), String::from("d e") },
+        String::from("f"),
+    });
+    let text = tree.root().to_indented_lines("    ").join("\n");
+    let parsed = Tree::from_indented(&text, 4).unwrap();
+    assert_eq!(tree, parsed);
+    assert_eq!(tree.root().to_indented_lines("    "), parsed.root().to_indented_lines("    "));
+}
+
+#[test]
+fn from_indented_errors() {
+    assert_eq!(Err(ParseError::ExpectedValue { offset: 3 }), Tree::from_indented("\n  ", 2));
+    assert_eq!(Err(ParseError::BadIndent { offset: 2 }), Tree::from_indented("a\n   b", 2));
+    assert_eq!(Err(ParseError::IndentJump { offset: 6 }), Tree::from_indented("a\n  b\n      c", 2));
+    assert_eq!(Err(ParseError::IndentJump { offset: 0 }), Tree::from_indented("  a", 2));
+    assert_eq!(Err(ParseError::TrailingInput { offset: 6 }), Tree::from_indented("a\n  b\nc", 2));
+}
+
+#[test]
+fn from_indented_tabs() {
+    assert_eq!(Err(ParseError::TabIndent { offset: 2 }), Tree::from_indented("a\n\tb", 2));
+    assert_eq!(Err(ParseError::TabIndent { offset: 4 }), Tree::from_indented("a\n  \tb", 2));
+    assert_eq!(
+        "tab in indentation at byte 2",
+        Tree::from_indented("a\n\tb", 1).unwrap_err().to_string()
+    );
+
+    // Tabs within a value, or making up a blank line, are not indentation.
+    let tree = Tree::from_indented("a\n\t\n  b\tc", 2).unwrap();
+    assert_eq!(vec!["a", "b\tc"], tree.values().map(String::as_str).collect::<Vec<_>>());
+}
+
+#[test]
+fn arity_histogram() {
+    let mut tree = tree!('a' => { 'b' => { 'c', 'd', 'e' }, 'f' => { 'g' }, 'h' });
+    let _ = tree.orphan('i');
+    let histogram = tree.arity_histogram();
+    assert_eq!(3, histogram.len());
+    assert_eq!(6, histogram[&0]);
+    assert_eq!(1, histogram[&1]);
+    assert_eq!(2, histogram[&3]);
+    assert_eq!(tree.len(), histogram.values().sum::<usize>());
+}
+
+#[test]
+fn walk_mut() {
+    struct Recorder(Vec<String>);
+
+    impl VisitorMut<i32> for Recorder {
+        fn enter(&mut self, _id: NodeId<i32>, depth: usize, value: &mut i32) {
+            *value += 10;
+            self.0.push(format!("+{}@{}", value, depth));
+        }
+
+        fn leave(&mut self, _id: NodeId<i32>, depth: usize, value: &mut i32) {
+            self.0.push(format!("-{}@{}", value, depth));
+        }
+    }
+
+    let mut tree = tree!(1 => { 2 => { 3 }, 4 });
+    let _ = tree.orphan(5);
+    let mut recorder = Recorder(Vec::new());
+    tree.walk_mut(&mut recorder);
+
+    assert_eq!(
+        vec!["+11@0", "+12@1", "+13@2", "-13@2", "-12@1", "+14@1", "-14@1", "-11@0"],
+        recorder.0
+    );
+    assert_eq!(vec![&11, &12, &13, &14, &5], tree.values().collect::<Vec<_>>());
+}
+
+#[test]
+fn into_parts() {
+    let mut tree = tree!('a' => { 'b' => { 'c' }, 'd' });
+    let c = tree.root().first_child().unwrap().first_child().unwrap().id();
+    let d = tree.root().last_child().unwrap().id();
+    let _ = tree.get_mut(c).drain_subtree();
+    let expected = tree.clone();
+    let (id, nodes) = tree.into_parts();
+
+    assert_eq!(d.tree_id(), id);
+    assert_eq!(4, nodes.len());
+    assert_eq!(Some('a'), nodes[0].1);
+    assert_eq!(Some((1, 3)), nodes[0].0.children);
+    assert_eq!((Links::default(), None), nodes[2]);
+
+    let rebuilt = Tree::from_parts(id, nodes).unwrap();
+    assert_eq!(expected, rebuilt);
+    assert_eq!(&'d', rebuilt.get(d).value());
+    assert_eq!(3, rebuilt.len());
+}
+
+#[test]
+#[should_panic(expected = "node ID is out of range")]
+fn from_parts_shorter_tree() {
+    let tree = tree!('a' => { 'b', 'c' => { 'd' } });
+    let d = tree.root().last_child().unwrap().first_child().unwrap().id();
+    let (id, mut nodes) = tree.into_parts();
+    nodes.truncate(2);
+    nodes[0].0.children = Some((1, 1));
+    nodes[1].0.next_sibling = None;
+    let shorter = Tree::from_parts(id, nodes).unwrap();
+    let _ = shorter.get(d);
+}
+
+#[test]
+fn from_parts_errors() {
+    let kind = |result: Result<Tree<char>, Inconsistency>| result.unwrap_err().kind;
+    let tree = tree!('a' => { 'b', 'c' });
+    assert_eq!(InconsistencyKind::NoRoot, kind(Tree::from_parts(0, Vec::new())));
+
+    let (id, mut nodes) = tree.clone().into_parts();
+    nodes[1].0.parent = Some(7);
+    assert_eq!(InconsistencyKind::LinkOutOfRange, kind(Tree::from_parts(id, nodes)));
+
+    let (id, mut nodes) = tree.clone().into_parts();
+    nodes[2].0.prev_sibling = None;
+    assert_eq!(InconsistencyKind::WrongPrevSibling, kind(Tree::from_parts(id, nodes)));
+
+    let (id, mut nodes) = tree.into_parts();
+    nodes[1].1 = None;
+    assert_eq!(InconsistencyKind::RemovedWithLinks, kind(Tree::from_parts(id, nodes)));
+}
+
+#[test]
+fn from_parts_parentless_siblings() {
+    let (id, mut nodes) = tree!('a' => { 'b' }).into_parts();
+    nodes[0].0.next_sibling = Some(1);
+    let error = Tree::from_parts(id, nodes).unwrap_err();
+    assert_eq!(0, error.node);
+    assert_eq!(InconsistencyKind::ParentlessWithSiblings, error.kind);
+
+    let mut tree = tree!('a' => { 'b' });
+    let _ = tree.orphan('x');
+    let (id, mut nodes) = tree.into_parts();
+    nodes[2].0.prev_sibling = Some(1);
+    let error = Tree::from_parts(id, nodes).unwrap_err();
+    assert_eq!(2, error.node);
+    assert_eq!(InconsistencyKind::ParentlessWithSiblings, error.kind);
+}
+
+#[test]
+fn deduplicate_subtrees() {
+    let s = String::from;
+    let mut tree = tree!(s("a") => {
+        s("b") => { s("c"), s("d") => { s("e") } },
+        s("x") => { s("b") => { s("c"), s("d") => { s("e") } } },
+        s("f"),
+    });
+    let first = tree.root().first_child().unwrap().id();
+    let mut replaced_with = Vec::new();
+    tree.deduplicate_subtrees(|id| { replaced_with.push(id); s("ref") });
+
+    assert_eq!(vec![first], replaced_with);
+    assert_eq!("(a (b c (d e)) (x ref) f)", tree.root().to_sexpr());
+    assert_eq!(8, tree.len());
+    assert_eq!(Ok(()), tree.sanity_check());
+}
+
+#[test]
+fn filter_map() {
+    let tree = tree!(1 => { 2 => { 3, 0 => { 4 } }, 0 => { 5 }, 6 });
+    let mut seen = Vec::new();
+    let mapped = tree.filter_map(|&n| {
+        seen.push(n);
+        if n == 0 { None } else { Some(n * 10) }
+    });
+    assert_eq!("(10 (20 30) 60)", mapped.root().to_sexpr());
+    assert_eq!(vec![1, 2, 3, 0, 0, 6], seen);
+}
+
+#[test]
+#[should_panic(expected = "filter_map dropped the root")]
+fn filter_map_root() {
+    let tree = tree!(0 => { 1 });
+    let _ = tree.filter_map(|&n| if n == 0 { None } else { Some(n) });
+}